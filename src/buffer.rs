@@ -32,11 +32,20 @@ impl Buffer {
                     std::ops::Bound::Unbounded => *size,
                 };
                 assert!((0..(*size)).contains(&start));
-                assert!((0..(*size)).contains(&end));
+                assert!((0..=(*size)).contains(&end));
                 BufferSlice::Vram { addr: unsafe{ (*addr).byte_add(start) }, size: (end-start) }
             },
         }
     }
+    pub(crate) fn addr(&self) -> *const c_void {
+        match self {
+            Buffer::Linear(x) => x.as_ptr().cast(),
+            Buffer::Vram { addr, .. } => *addr,
+        }
+    }
+    pub(crate) fn phys_addr(&self) -> u32 {
+        unsafe { ctru_sys::osConvertVirtToPhys(self.addr()) }
+    }
 }
 
 impl Drop for Buffer {
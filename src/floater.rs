@@ -32,3 +32,68 @@ pub fn f32x4tof24x4(f: [f32;4]) -> [u32;3] {
         x | ((y & 0xFF) << 24)
     ]
 }
+
+///Inverse of `f32tof24`, an exponent of 0 is read as zero
+pub fn f24tof32(f: u32) -> f32 {
+    let sign = (f >> 23) & 1;
+    let exponent = (f >> 16) & 0x7F;
+    let mantissa = f & 0xFFFF;
+    let bits = match exponent {
+        0 => sign << 31,
+        0x7F => sign << 31 | 0xFF << 23 | mantissa << 7,
+        _ => sign << 31 | (exponent + 127 - 63) << 23 | mantissa << 7,
+    };
+    f32::from_bits(bits)
+}
+
+///Inverse of `f32x4tof24x4`, XYZW order
+pub fn f24x4tof32x4(f: [u32;3]) -> [f32;4] {
+    [
+        f[2] & 0xFFFFFF,
+        ((f[1] & 0xFFFF) << 8) | (f[2] >> 24),
+        ((f[0] & 0xFF) << 16) | (f[1] >> 16),
+        f[0] >> 8,
+    ].map(f24tof32)
+}
+
+///Converts to a float with `exponent_bits` and `mantissa_bits`, plus a sign bit on top.
+///Like `f32tof24`, the mantissa is truncated, underflow flushes to zero and overflow saturates.
+fn f32tofloat(f: f32, exponent_bits: u32, mantissa_bits: u32) -> u32 {
+    let i: u32 = f.to_bits();
+    let mantissa = (i & 0x7FFFFF) >> (23 - mantissa_bits);
+    let exponent = ((i >> 23) & 0xFF) as i32;
+    let sign = i >> 31;
+    let shift = exponent_bits + mantissa_bits;
+    let max = (1 << exponent_bits) - 1;
+    let bias = (1 << (exponent_bits - 1)) - 1;
+    let exponent = exponent - 127 + bias;
+    if exponent <= 0 {
+        sign << shift
+    } else if exponent > max {
+        sign << shift | (max as u32) << mantissa_bits
+    } else {
+        sign << shift | (exponent as u32) << mantissa_bits | mantissa
+    }
+}
+
+///1.5.10 float, as used by light positions
+pub fn f32tof16(f: f32) -> u32 {
+    f32tofloat(f, 5, 10)
+}
+
+///1.7.12 float, as used by light attenuation
+pub fn f32tof20(f: f32) -> u32 {
+    f32tofloat(f, 7, 12)
+}
+
+///Signed fixed point with `fraction_bits` in a field of `bits`
+pub fn f32tofix(f: f32, bits: u32, fraction_bits: u32) -> u32 {
+    let max = ((1 << (bits - 1)) - 1) as f32;
+    let v = (f * (1 << fraction_bits) as f32).round().clamp(-max - 1.0, max);
+    (v as i32 as u32) & ((1 << bits) - 1)
+}
+
+///Signed 1.1.11 fixed point, as used by spotlight directions
+pub fn f32tofix13(f: f32) -> u32 {
+    f32tofix(f, 13, 11)
+}
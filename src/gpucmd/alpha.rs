@@ -1,6 +1,6 @@
-use ctru_sys::{GPUREG_BLEND_FUNC, GPUREG_FRAGOP_ALPHA_TEST};
+use super::regs::{GPUREG_BLEND_FUNC, GPUREG_FRAGOP_ALPHA_TEST};
 
-use super::{mask, GpuCmd};
+use super::{mask, GpuCmd, GpuCmdDisable};
 
 #[derive(Clone, Copy)]
 #[repr(u8)]
@@ -108,8 +108,10 @@ pub enum Function {
     GreaterThanOrEqual,
 }
 
-///In modern engines, you might know this as Alpha Clip or Alpha Threshold
+///In modern engines, you might know this as Alpha Clip or Alpha Threshold.
+///Subtract to write it with the test disabled.
 #[doc(alias = "Clip")]
+#[doc(alias = "Disable")]
 #[derive(Clone, Copy)]
 pub struct Test {
     pub enabled: bool,
@@ -125,16 +127,35 @@ impl Test {
             reference_value: 0x0000,
         }
     }
+    ///Passes fragments whose alpha compares to `reference_value` with `function`
+    pub fn new(function: Function, reference_value: u8) -> Test {
+        Test {
+            enabled: true,
+            function,
+            reference_value: reference_value as u16,
+        }
+    }
 }
 
 impl GpuCmd for Test {
     type Out = [u32; 2];
     fn cmd(self) -> Self::Out {
         [
-            if self.enabled { 0 } else { 1 }
+            if self.enabled { 1 } else { 0 }
                 | ((self.function as u32) << 4)
-                | ((self.reference_value as u32) << 8),
+                | (((self.reference_value & 0xFF) as u32) << 8),
             GPUREG_FRAGOP_ALPHA_TEST | mask(0xF),
         ]
     }
 }
+
+impl GpuCmdDisable for Test {
+    type Out = [u32; 2];
+    fn cmd_disable(self) -> Self::Out {
+        Test {
+            enabled: false,
+            ..self
+        }
+        .cmd()
+    }
+}
@@ -0,0 +1,243 @@
+//! Vertex attributes, loaded from up to 12 buffers.
+//!
+//! `Layout` says what each attribute looks like and which vertex shader input register it goes to,
+//! `Buffers` says where the attributes are in memory and how they are interleaved.
+//! Attributes added with `Layout::add_fixed` aren't loaded from a buffer, every vertex gets the same value.
+//!
+//! ```rust,ignore
+//! Root
+//!     + Layout::new().add(0, Type::Float, 3).add(1, Type::UnsignedByte, 4)
+//!     + Buffers::new().add(&vertices, 0, 16, &[Component::Attribute(0), Component::Attribute(1)])
+//!     + DrawArrays { mode: primitive::Mode::Triangles, first: 0, count: 3 }
+//! ```
+
+use super::regs::*;
+
+use super::{
+    CONSECUTIVE_WRITING, GpuCmd, GpuCmdByMut, extra_params, fixed_attrib, mask, transfer::Transfer,
+};
+#[cfg(target_os = "horizon")]
+use crate::buffer::Buffer;
+
+///https://www.3dbrew.org/wiki/GPU/Internal_Registers#GPUREG_ATTRIBBUFFERS_FORMAT_LOW
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u32)]
+pub enum Type {
+    Byte,
+    UnsignedByte,
+    Short,
+    Float,
+}
+
+impl Type {
+    pub fn bytesize(self) -> usize {
+        match self {
+            Type::Byte | Type::UnsignedByte => 1,
+            Type::Short => 2,
+            Type::Float => 4,
+        }
+    }
+}
+
+///Maps attributes to vertex shader input registers
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Layout {
+    ///`ATTRIBBUFFERS_FORMAT_LOW` and the low 16 bits of `ATTRIBBUFFERS_FORMAT_HIGH`
+    formats: u64,
+    ///`VSH_ATTRIBUTES_PERMUTATION_LOW/HIGH`
+    permutation: u64,
+    count: u32,
+    ///Bit `i` is set if attribute `i` is fixed
+    fixed: u16,
+    fixed_values: [[f32; 4]; 12],
+}
+
+impl Layout {
+    pub fn new() -> Self {
+        Default::default()
+    }
+    ///Adds the next attribute, `components` is `1..=4`.
+    ///`reg` is the vertex shader input register (`v0..=v15`) it is loaded into.
+    ///The attribute's index, as used by `Component::Attribute`, is the number of attributes added before it.
+    pub fn add(mut self, reg: u32, ty: Type, components: u32) -> Self {
+        assert!((1..=4).contains(&components), "Attributes have 1 to 4 components");
+        let id = self.push(reg) as u64;
+        self.formats |= ((ty as u64) | ((components as u64 - 1) << 2)) << (id * 4);
+        self
+    }
+    ///Adds the next attribute as a fixed one, every vertex gets `value` (XYZW) in `reg`.
+    ///Fixed attributes still take up an index, but are never listed in `Buffers`.
+    ///Use `fixed_attrib::Value` with its index to change the value later on.
+    pub fn add_fixed(mut self, reg: u32, value: [f32; 4]) -> Self {
+        let id = self.push(reg);
+        self.fixed |= 1 << id;
+        self.fixed_values[id as usize] = value;
+        self
+    }
+    ///Returns the new attribute's index
+    fn push(&mut self, reg: u32) -> u32 {
+        assert!(self.count < 12, "There can only be 12 attributes");
+        assert!(reg < 16, "Input registers are v0..=v15");
+        let id = self.count;
+        self.permutation |= (reg as u64) << (id * 4);
+        self.count += 1;
+        id
+    }
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+impl GpuCmdByMut for Layout {
+    fn cmd_by_mut<A: std::alloc::Allocator>(self, buf: &mut Vec<u32, A>) {
+        assert!(self.count > 0, "A layout needs at least one attribute");
+        let last = self.count - 1;
+        buf.extend_from_slice(&[
+            self.formats as u32,
+            GPUREG_ATTRIBBUFFERS_FORMAT_LOW | mask(0xF) | extra_params(1) | CONSECUTIVE_WRITING,
+            ((self.formats >> 32) as u32 & 0xFFFF) | ((self.fixed as u32) << 16) | (last << 28),
+            0,
+            0xA0000000 | last,
+            GPUREG_VSH_INPUTBUFFER_CONFIG | mask(0xB),
+            last,
+            GPUREG_VSH_NUM_ATTR | mask(0xF),
+            self.permutation as u32,
+            GPUREG_VSH_ATTRIBUTES_PERMUTATION_LOW
+                | mask(0xF)
+                | extra_params(1)
+                | CONSECUTIVE_WRITING,
+            (self.permutation >> 32) as u32,
+            0,
+        ]);
+        for id in 0..self.count {
+            if self.fixed & (1 << id) != 0 {
+                buf.extend_from_slice(&fixed_attrib::Value(id, self.fixed_values[id as usize]).cmd());
+            }
+        }
+    }
+}
+
+///What a buffer stores for each vertex, in order
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Component {
+    ///Index into the `Layout`
+    Attribute(u8),
+    ///Skips `4`, `8`, `12` or `16` bytes
+    Padding(u8),
+}
+
+#[cfg(target_os = "horizon")]
+impl Component {
+    fn bits(self) -> u64 {
+        match self {
+            Component::Attribute(id) => {
+                assert!(id < 12, "There are only 12 attributes");
+                id as u64
+            }
+            Component::Padding(bytes) => {
+                assert!(matches!(bytes, 4 | 8 | 12 | 16), "Padding is 4, 8, 12 or 16 bytes");
+                0xB + (bytes as u64 / 4)
+            }
+        }
+    }
+}
+
+///Where the attributes come from.
+///Offsets are relative to the start of VRAM, which is below linear memory, so buffers can be in either.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Buffers {
+    ///`OFFSET`, `CONFIG1` and `CONFIG2` of each buffer
+    buffers: [[u32; 3]; 12],
+    count: usize,
+}
+
+impl Buffers {
+    ///Physical address of VRAM
+    pub const BASE: u32 = 0x18000000;
+    pub fn new() -> Self {
+        Default::default()
+    }
+    ///Adds a buffer whose vertices start `offset` bytes into `buffer` and are `stride` bytes apart.
+    ///`components` is at most 12 entries long.
+    #[cfg(target_os = "horizon")]
+    pub fn add(mut self, buffer: &Buffer, offset: usize, stride: u8, components: &[Component]) -> Self {
+        assert!(self.count < 12, "There can only be 12 buffers");
+        assert!(components.len() <= 12, "A buffer can only hold 12 components");
+        let permutation = components
+            .iter()
+            .enumerate()
+            .fold(0, |acc, (i, c)| acc | (c.bits() << (i * 4)));
+        self.buffers[self.count] = [
+            Self::offset_of(buffer, offset),
+            permutation as u32,
+            ((permutation >> 32) as u32 & 0xFFFF)
+                | ((stride as u32) << 16)
+                | ((components.len() as u32) << 28),
+        ];
+        self.count += 1;
+        self
+    }
+    ///Offset of `buffer` plus `offset` from `BASE`
+    #[cfg(target_os = "horizon")]
+    pub fn offset_of(buffer: &Buffer, offset: usize) -> u32 {
+        let phys = buffer.phys_addr() + offset as u32;
+        assert!(phys >= Self::BASE, "Buffer must be in linear memory or VRAM");
+        phys - Self::BASE
+    }
+}
+
+impl GpuCmdByMut for Buffers {
+    fn cmd_by_mut<A: std::alloc::Allocator>(self, buf: &mut Vec<u32, A>) {
+        buf.extend_from_slice(&[Self::BASE >> 3, GPUREG_ATTRIBBUFFERS_LOC | mask(0xF)]);
+        Transfer {
+            reg: GPUREG_ATTRIBBUFFER0_OFFSET | mask(0xF) | CONSECUTIVE_WRITING,
+            data: self.buffers.as_flattened(),
+        }
+        .cmd_by_mut(buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_words() {
+        let mut buf = Vec::new();
+        Layout::new().add(0, Type::Float, 4).add(3, Type::UnsignedByte, 2).cmd_by_mut(&mut buf);
+        assert_eq!(
+            buf,
+            [
+                //Float with 4 components, then UnsignedByte with 2
+                0x5F,
+                GPUREG_ATTRIBBUFFERS_FORMAT_LOW | mask(0xF) | extra_params(1) | CONSECUTIVE_WRITING,
+                //No fixed attributes, 2 in total
+                1 << 28,
+                0,
+                0xA0000001,
+                GPUREG_VSH_INPUTBUFFER_CONFIG | mask(0xB),
+                1,
+                GPUREG_VSH_NUM_ATTR | mask(0xF),
+                //v0, then v3
+                0x30,
+                GPUREG_VSH_ATTRIBUTES_PERMUTATION_LOW | mask(0xF) | extra_params(1) | CONSECUTIVE_WRITING,
+                0,
+                0,
+            ]
+        );
+    }
+
+    #[test]
+    fn fixed_attribute_words() {
+        let value = [1.0, 0.5, 0.0, 1.0];
+        let mut buf = Vec::new();
+        Layout::new().add(0, Type::Float, 3).add_fixed(2, value).cmd_by_mut(&mut buf);
+        //Attribute 1 is marked fixed in FORMAT_HIGH
+        assert_eq!(buf[2], 1 << (16 + 1) | 1 << 28);
+        //v0, then v2
+        assert_eq!(buf[8], 0x20);
+        //Its value follows the layout
+        assert_eq!(buf[12..], fixed_attrib::Value(1, value).cmd());
+        assert_eq!(buf[12..14], [1, GPUREG_FIXEDATTRIB_INDEX | mask(0xF)]);
+    }
+}
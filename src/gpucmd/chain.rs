@@ -3,7 +3,7 @@
 //!
 //! Please note that when implementing chains, you can only have `0xFF` extra params after the first param
 //!
-//! ```rust
+//! ```rust,ignore
 //! Root + Chain * shader.uniform("twovecs") * UniformF24(twovecs)
 //! Root + shader.uniform("matrix") + UniformF24(matrix)
 //! ```
@@ -135,8 +135,25 @@ impl<M: ChainContinueByMut, R: ChainableByMut> GpuCmdByMut for ChainMore<R, M> {
     fn cmd_by_mut<A: Allocator>(self, buf: &mut Vec<u32, A>) {
         self.root.start_chain_by_mut(self.size, buf);
         self.more.continue_chain_by_mut(buf);
-        if (self.size & 1) == 0 {
+        //`size` is the number of extra params, commands with an odd number of them are padded
+        if (self.size & 1) == 1 {
             buf.push(0);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gpucmd::texunit::{BorderColor, Dim, U0, TexUnit};
+
+    #[test]
+    fn odd_extra_params_are_padded() {
+        let mut buf = Vec::new();
+        (Chain * BorderColor(1, U0) * Dim { width: 16, height: 8, tu: U0 }).cmd_by_mut(&mut buf);
+        assert_eq!(
+            buf,
+            [1, U0::BASE | mask(0xF) | extra_params(1) | CONSECUTIVE_WRITING, 8 | (16 << 16), 0]
+        );
+    }
+}
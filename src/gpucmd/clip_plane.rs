@@ -0,0 +1,70 @@
+//! A user clip plane, cutting geometry on top of the clip volume.
+//!
+//! ```rust,ignore
+//! //Only what is above the water, for the reflection pass
+//! Root + clip_plane::ClipPlane([0.0, 1.0, 0.0, -water_height])
+//! //And off again
+//! Root - clip_plane::ClipPlane::default()
+//! ```
+//!
+//! The plane is in clip space, so transform it by the inverse transpose of the projection matrix first.
+//! https://www.3dbrew.org/wiki/GPU/Internal_Registers#GPUREG_FRAGOP_CLIP
+
+use super::regs::*;
+
+use super::{CONSECUTIVE_WRITING, GpuCmd, GpuCmdDisable, extra_params, mask};
+use crate::floater::f32tof24;
+
+///`[a,b,c,d]`, keeps positions where `a*x + b*y + c*z + d*w >= 0`.
+///Subtract to Disable
+#[doc(alias = "Disable")]
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct ClipPlane(pub [f32; 4]);
+
+impl GpuCmd for ClipPlane {
+    type Out = [u32; 8];
+    fn cmd(self) -> Self::Out {
+        let [a, b, c, d] = self.0.map(f32tof24);
+        [
+            1,
+            GPUREG_FRAGOP_CLIP | mask(0xF),
+            a,
+            GPUREG_FRAGOP_CLIP_DATA0 | mask(0xF) | extra_params(3) | CONSECUTIVE_WRITING,
+            b,
+            c,
+            d,
+            0,
+        ]
+    }
+}
+
+impl GpuCmdDisable for ClipPlane {
+    type Out = [u32; 2];
+    fn cmd_disable(self) -> Self::Out {
+        [0, GPUREG_FRAGOP_CLIP | mask(0xF)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn words() {
+        assert_eq!(
+            ClipPlane([0.0, 1.0, -2.0, 0.5]).cmd(),
+            [
+                1,
+                GPUREG_FRAGOP_CLIP | mask(0xF),
+                f32tof24(0.0),
+                GPUREG_FRAGOP_CLIP_DATA0 | mask(0xF) | extra_params(3) | CONSECUTIVE_WRITING,
+                f32tof24(1.0),
+                f32tof24(-2.0),
+                f32tof24(0.5),
+                //Padding
+                0,
+            ]
+        );
+        assert_eq!(ClipPlane::default().cmd_disable(), [0, GPUREG_FRAGOP_CLIP | mask(0xF)]);
+    }
+}
@@ -1,15 +1,15 @@
-use ctru_sys::*;
-use super::{GpuCmd,mask};
+use super::regs::*;
+use super::{GpuCmd,mask,alpha::Blend,logic_op::LogicOp};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 #[repr(u8)]
 pub enum FragOp {
-    Default,
-    Gas,
-    Shadow
+    Default = 0,
+    Gas = 1,
+    Shadow = 3
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 #[repr(u8)]
 pub enum BlendMode {
     LogicOp,
@@ -25,3 +25,26 @@ impl GpuCmd for ColorOperation {
         [u32::from_le_bytes([self.0 as u8,self.1 as u8,0xE4,0x00]),GPUREG_COLOR_OPERATION | mask(0xF)]
     }
 }
+
+///Either blending or a logic op, together with the matching `ColorOperation`.
+///Uses `FragOp::Default`, gas and shadow passes still need `ColorOperation` on its own.
+///```rust,ignore
+///Root + FragmentOutput::Blend(alpha::Blend::new(alpha::Add, alpha::Add, alpha::SrcAlpha, alpha::OneMinusSrcAlpha, alpha::One, alpha::Zero))
+///Root + FragmentOutput::LogicOp(logic_op::Xor)
+///```
+#[derive(Clone, Copy)]
+pub enum FragmentOutput {
+    Blend(Blend),
+    LogicOp(LogicOp)
+}
+
+impl GpuCmd for FragmentOutput {
+    type Out = [u32;4];
+    fn cmd(self) -> Self::Out {
+        let ([a,b],[c,d]) = match self {
+            FragmentOutput::Blend(blend) => (ColorOperation(FragOp::Default,BlendMode::Blend).cmd(),blend.cmd()),
+            FragmentOutput::LogicOp(op) => (ColorOperation(FragOp::Default,BlendMode::LogicOp).cmd(),op.cmd()),
+        };
+        [a,b,c,d]
+    }
+}
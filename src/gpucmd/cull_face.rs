@@ -1,6 +1,6 @@
-use ctru_sys::*;
+use super::regs::*;
 
-use super::{mask, GpuCmd};
+use super::{mask, GpuCmd, GpuCmdDisable};
 
 #[derive(Clone, Copy)]
 pub struct No;
@@ -31,3 +31,56 @@ impl GpuCmd for BackCCW {
         [2,GPUREG_FACECULLING_CONFIG | mask(0xF)]
     }
 }
+
+///Which faces `GPUREG_FACECULLING_CONFIG` culls, named by the face culled and the winding of front faces.
+///The hardware only knows about counter clockwise culling, the clockwise modes flip it.
+///Subtract to Disable
+#[doc(alias = "Disable")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CullMode {
+    None,
+    FrontCCW,
+    BackCCW,
+    FrontCW,
+    BackCW,
+}
+
+impl CullMode {
+    ///The same faces, with the opposite winding
+    pub fn flipped(self) -> CullMode {
+        use CullMode::*;
+        match self {
+            None => None,
+            FrontCCW => FrontCW,
+            BackCCW => BackCW,
+            FrontCW => FrontCCW,
+            BackCW => BackCCW,
+        }
+    }
+    ///A model matrix with a negative determinant mirrors the winding of every triangle
+    pub fn for_determinant(self, determinant: f32) -> CullMode {
+        if determinant < 0.0 { self.flipped() } else { self }
+    }
+    fn param(self) -> u32 {
+        use CullMode::*;
+        match self {
+            None => 0,
+            FrontCCW | BackCW => 1,
+            BackCCW | FrontCW => 2,
+        }
+    }
+}
+
+impl GpuCmd for CullMode {
+    type Out = [u32;2];
+    fn cmd(self) -> Self::Out {
+        [self.param(),GPUREG_FACECULLING_CONFIG | mask(0xF)]
+    }
+}
+
+impl GpuCmdDisable for CullMode {
+    type Out = [u32;2];
+    fn cmd_disable(self) -> Self::Out {
+        CullMode::None.cmd()
+    }
+}
@@ -1,5 +1,5 @@
-use ctru_sys::*;
-use super::{mask, GpuCmd};
+use super::regs::*;
+use super::{mask, GpuCmd, GpuCmdByMut, GpuCmdDisable, Root};
 
 #[derive(Clone,Copy)]
 #[repr(u32)]
@@ -43,3 +43,150 @@ impl GpuCmd for DepthColorMask {
         ]
     }
 }
+
+///Bits 8 to 12 of `GPUREG_DEPTH_COLOR_MASK`, combine with `|`
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub struct WriteMask(pub u8);
+
+impl WriteMask {
+    pub const NONE: WriteMask = WriteMask(0);
+    pub const RED: WriteMask = WriteMask(1);
+    pub const GREEN: WriteMask = WriteMask(2);
+    pub const BLUE: WriteMask = WriteMask(4);
+    pub const ALPHA: WriteMask = WriteMask(8);
+    pub const DEPTH: WriteMask = WriteMask(0x10);
+    pub const COLOR: WriteMask = WriteMask(0xF);
+    pub const ALL: WriteMask = WriteMask(0x1F);
+    pub fn contains(self, other: WriteMask) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for WriteMask {
+    type Output = WriteMask;
+    fn bitor(self, rhs: WriteMask) -> WriteMask {
+        WriteMask(self.0 | rhs.0)
+    }
+}
+
+impl From<DepthTest> for DepthColorMask {
+    fn from(DepthTest(function, writes): DepthTest) -> Self {
+        DepthColorMask {
+            enabled: true,
+            function,
+            red_write: writes.contains(WriteMask::RED),
+            green_write: writes.contains(WriteMask::GREEN),
+            blue_write: writes.contains(WriteMask::BLUE),
+            alpha_write: writes.contains(WriteMask::ALPHA),
+            depth_write: writes.contains(WriteMask::DEPTH)
+        }
+    }
+}
+
+///Enables the depth test, `DepthTest(Function::GreaterThan, WriteMask::ALL)`.
+///Subtract to disable both the test and depth writes, keeping the color writes.
+///
+///Shares its register with `ColorWriteMask`, add one to the other to write both at once:
+///```rust,ignore
+///Root + (DepthTest(Function::LessThan, WriteMask::ALL) + ColorWriteMask(WriteMask::RED | WriteMask::ALPHA | WriteMask::DEPTH))
+///```
+#[doc(alias = "Disable")]
+#[derive(Clone,Copy)]
+pub struct DepthTest(pub Function, pub WriteMask);
+
+impl GpuCmd for DepthTest {
+    type Out = [u32;2];
+    fn cmd(self) -> Self::Out {
+        DepthColorMask::from(self).cmd()
+    }
+}
+
+impl GpuCmdDisable for DepthTest {
+    type Out = [u32;2];
+    fn cmd_disable(self) -> Self::Out {
+        DepthColorMask {
+            enabled: false,
+            depth_write: false,
+            ..self.into()
+        }.cmd()
+    }
+}
+
+///Which channels are written, `WriteMask::DEPTH` included, leaving the depth test alone.
+///
+///`GPUREG_DEPTH_COLOR_MASK` can only be written a byte at a time and the depth write bit shares a byte with the color bits,
+///so it's always written too.
+#[derive(Clone,Copy)]
+pub struct ColorWriteMask(pub WriteMask);
+
+impl GpuCmd for ColorWriteMask {
+    type Out = [u32;2];
+    fn cmd(self) -> Self::Out {
+        [((self.0.0 & WriteMask::ALL.0) as u32) << 8, GPUREG_DEPTH_COLOR_MASK | mask(0b0010)]
+    }
+}
+
+///Replaces the writes of the depth test, like encoding one after the other but in a single write
+impl std::ops::Add<ColorWriteMask> for DepthTest {
+    type Output = DepthTest;
+    fn add(self, rhs: ColorWriteMask) -> DepthTest {
+        DepthTest(self.0, rhs.0)
+    }
+}
+
+///`GPUREG_DEPTHBUFFER_READ` and `GPUREG_DEPTHBUFFER_WRITE`, for depth and stencil alike.
+///With both off, the depth buffer is never touched.
+#[derive(Clone,Copy)]
+pub struct DepthBufferAccess {
+    pub read: bool,
+    pub write: bool
+}
+
+impl GpuCmd for DepthBufferAccess {
+    type Out = [u32;4];
+    fn cmd(self) -> Self::Out {
+        [
+            if self.read {3} else {0},
+            GPUREG_DEPTHBUFFER_READ | mask(0xF),
+            if self.write {3} else {0},
+            GPUREG_DEPTHBUFFER_WRITE | mask(0xF)
+        ]
+    }
+}
+
+///No depth test, no depth writes, and no depth buffer access, only writing `colors`
+pub fn NoDepth(colors: WriteMask) -> impl GpuCmdByMut + Copy {
+    Root - DepthTest(Function::Always, colors) + DepthBufferAccess { read: false, write: false }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_write_mask_leaves_depth_test() {
+        assert_eq!(
+            ColorWriteMask(WriteMask::RED | WriteMask::ALPHA | WriteMask::DEPTH).cmd(),
+            [0b11001 << 8, GPUREG_DEPTH_COLOR_MASK | mask(0b0010)]
+        );
+        assert_eq!(ColorWriteMask(WriteMask::COLOR).cmd()[0], 0xF << 8);
+    }
+
+    #[test]
+    fn depth_test_plus_color_write_mask() {
+        assert_eq!(
+            (DepthTest(Function::LessThan, WriteMask::ALL) + ColorWriteMask(WriteMask::GREEN | WriteMask::DEPTH)).cmd(),
+            [1 | (Function::LessThan as u32) << 4 | 1 << 9 | 1 << 12, GPUREG_DEPTH_COLOR_MASK | mask(0xF)]
+        );
+    }
+
+    #[test]
+    fn no_depth() {
+        let mut buf = Vec::new();
+        NoDepth(WriteMask::GREEN).cmd_by_mut(&mut buf);
+        assert_eq!(
+            buf[..2],
+            [(Function::Always as u32) << 4 | 1 << 9, GPUREG_DEPTH_COLOR_MASK | mask(0xF)]
+        );
+    }
+}
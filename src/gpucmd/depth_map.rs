@@ -1,7 +1,9 @@
 use super::{mask, GpuCmd, GpuCmdByMut, GpuCmdDisable, Root};
-use ctru_sys::*;
+use super::regs::*;
+use crate::floater::f32tof24;
 
-///Subtract to Disable
+///Z-buffering, depth is `z / w * scale + offset`.
+///Subtract to Disable, for W-buffering, which multiplies that by `w`
 #[doc(alias = "Disable")]
 #[derive(Clone, Copy,PartialEq, Eq)]
 pub struct Enabled;
@@ -26,7 +28,7 @@ pub struct Scale(pub f32);
 impl GpuCmd for Scale {
     type Out = [u32; 2];
     fn cmd(self) -> Self::Out {
-        [unsafe { f32tof24(self.0) }, GPUREG_DEPTHMAP_SCALE | mask(0xF)]
+        [f32tof24(self.0), GPUREG_DEPTHMAP_SCALE | mask(0xF)]
     }
 }
 
@@ -36,10 +38,96 @@ pub struct Offset(pub f32);
 impl GpuCmd for Offset {
     type Out = [u32; 2];
     fn cmd(self) -> Self::Out {
-        [unsafe { f32tof24(self.0) }, GPUREG_DEPTHMAP_OFFSET | mask(0xF)]
+        [f32tof24(self.0), GPUREG_DEPTHMAP_OFFSET | mask(0xF)]
     }
 }
 
-pub fn EnabledScaleOffset(scale: f32, offset: f32) -> impl GpuCmdByMut + Clone + Copy {
+pub fn EnabledScaleOffset(scale: f32, offset: f32) -> impl GpuCmdByMut + Copy {
     Root + Enabled + Scale(scale) + Offset(offset)
 }
+
+///Maps clip space depth, where `z / w` is `0` at the near plane and `-1` at the far plane,
+///to `near..far` in the depth buffer. `DepthRange(1.0, 0.0)` goes with a `GreaterThan` depth test.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DepthRange(pub f32, pub f32);
+
+impl DepthRange {
+    pub fn scale(self) -> f32 {
+        self.0 - self.1
+    }
+    pub fn offset(self) -> f32 {
+        self.0
+    }
+    ///Moves everything `units` steps of a `format` depth buffer towards the near plane,
+    ///so decals win against the surface they lie on.
+    #[doc(alias = "Bias")]
+    pub fn polygon_offset(self, units: f32, format: DepthFormat) -> DepthRange {
+        let step = units * (self.0 - self.1).signum() / ((1u32 << format.bits()) - 1) as f32;
+        DepthRange(self.0 + step, self.1 + step)
+    }
+}
+
+///https://www.3dbrew.org/wiki/GPU/Internal_Registers#GPUREG_DEPTHBUFFER_FORMAT
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u32)]
+pub enum DepthFormat {
+    D16 = 0,
+    D24 = 2,
+    D24S8 = 3,
+}
+
+impl DepthFormat {
+    ///Bits of depth, the stencil not included
+    pub fn bits(self) -> u32 {
+        match self {
+            DepthFormat::D16 => 16,
+            DepthFormat::D24 | DepthFormat::D24S8 => 24,
+        }
+    }
+}
+
+impl GpuCmd for DepthRange {
+    type Out = [u32; 4];
+    fn cmd(self) -> Self::Out {
+        let [a, b] = Scale(self.scale()).cmd();
+        let [c, d] = Offset(self.offset()).cmd();
+        [a, b, c, d]
+    }
+}
+
+pub fn ZBuffer(range: DepthRange) -> impl GpuCmdByMut + Copy {
+    Root + Enabled + range
+}
+
+///Depth is `z * scale + w * offset` here, so pick `range` so that it stays within `0..1` at the far plane
+pub fn WBuffer(range: DepthRange) -> impl GpuCmdByMut + Copy {
+    Root - Enabled + range
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scale_offset() {
+        assert_eq!((DepthRange(0.0, 1.0).scale(), DepthRange(0.0, 1.0).offset()), (-1.0, 0.0));
+        assert_eq!((DepthRange(1.0, 0.0).scale(), DepthRange(1.0, 0.0).offset()), (1.0, 1.0));
+        //The near plane maps to the first value and the far plane to the second
+        let range = DepthRange(0.25, 0.75);
+        assert_eq!(range.offset(), 0.25);
+        assert_eq!(range.offset() - range.scale(), 0.75);
+    }
+
+    #[test]
+    fn polygon_offset_moves_towards_the_near_plane() {
+        let step = 1.0 / 65535.0;
+        assert_eq!(DepthRange(0.0, 1.0).polygon_offset(2.0, DepthFormat::D16), DepthRange(-2.0 * step, 1.0 - 2.0 * step));
+        //Reversed, near is the larger value
+        assert_eq!(DepthRange(1.0, 0.0).polygon_offset(2.0, DepthFormat::D16), DepthRange(1.0 + 2.0 * step, 2.0 * step));
+        let step = 1.0 / 16777215.0;
+        assert_eq!(DepthRange(0.0, 1.0).polygon_offset(1.0, DepthFormat::D24S8), DepthRange(-step, 1.0 - step));
+        //Only the offset changes
+        let range = DepthRange(1.0, 0.0);
+        assert_eq!(range.polygon_offset(1.0, DepthFormat::D24).scale(), range.scale());
+    }
+}
@@ -0,0 +1,121 @@
+//! Drawing from the attribute buffers set up with `attribute::Buffers`, optionally through an index buffer.
+//!
+//! Each draw sets the primitive mode, restarts the primitive, switches into drawing mode and back,
+//! and clears the post vertex cache, so none of that has to be done by hand.
+
+use super::regs::*;
+
+use super::{GpuCmd, mask, primitive::Mode};
+#[cfg(target_os = "horizon")]
+use {super::{GpuCmdByMut, attribute::Buffers}, crate::buffer::Buffer};
+
+///Draws `count` vertices, starting at vertex `first` of every attribute buffer
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DrawArrays {
+    pub mode: Mode,
+    pub first: u32,
+    pub count: u32,
+}
+
+impl GpuCmd for DrawArrays {
+    type Out = [u32; 22];
+
+    fn cmd(self) -> Self::Out {
+        [
+            (self.mode as u32) << 8,
+            GPUREG_PRIMITIVE_CONFIG | mask(0x2),
+            1,
+            GPUREG_RESTART_PRIMITIVE | mask(0xF),
+            //The index buffer isn't used, but has to be configured anyway
+            0x80000000,
+            GPUREG_INDEXBUFFER_CONFIG | mask(0xF),
+            self.count,
+            GPUREG_NUMVERTICES | mask(0xF),
+            self.first,
+            GPUREG_VERTEX_OFFSET | mask(0xF),
+            1,
+            GPUREG_GEOSTAGE_CONFIG2 | mask(0x1),
+            0,
+            GPUREG_START_DRAW_FUNC0 | mask(0x1),
+            1,
+            GPUREG_DRAWARRAYS | mask(0xF),
+            1,
+            GPUREG_START_DRAW_FUNC0 | mask(0x1),
+            0,
+            GPUREG_GEOSTAGE_CONFIG2 | mask(0x1),
+            1,
+            GPUREG_VTX_FUNC | mask(0xF),
+        ]
+    }
+}
+
+///https://www.3dbrew.org/wiki/GPU/Internal_Registers#GPUREG_INDEXBUFFER_CONFIG
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u32)]
+pub enum IndexType {
+    U8,
+    U16,
+}
+
+///Draws `count` vertices, whose indices start `offset` bytes into `indices`.
+///`indices` must be in linear memory or VRAM, like the attribute buffers.
+#[cfg(target_os = "horizon")]
+#[derive(Clone, Copy)]
+pub struct DrawElements<'a> {
+    pub mode: Mode,
+    pub indices: &'a Buffer,
+    pub offset: usize,
+    pub index_type: IndexType,
+    pub count: u32,
+}
+
+#[cfg(target_os = "horizon")]
+impl<'a> GpuCmdByMut for DrawElements<'a> {
+    fn cmd_by_mut<A: std::alloc::Allocator>(self, buf: &mut Vec<u32, A>) {
+        let offset = Buffers::offset_of(self.indices, self.offset);
+        assert!(
+            self.index_type == IndexType::U8 || offset % 2 == 0,
+            "16 bit indices must be 2 byte aligned"
+        );
+        //Triangle lists are drawn as geometry primitives, with the geometry stage in element mode
+        let triangles = self.mode == Mode::Triangles;
+        let mode = if triangles { Mode::GeometryPrimitive } else { self.mode };
+        buf.extend_from_slice(&[
+            (mode as u32) << 8,
+            GPUREG_PRIMITIVE_CONFIG | mask(0x2),
+            1,
+            GPUREG_RESTART_PRIMITIVE | mask(0xF),
+            offset | ((self.index_type as u32) << 31),
+            GPUREG_INDEXBUFFER_CONFIG | mask(0xF),
+            self.count,
+            GPUREG_NUMVERTICES | mask(0xF),
+            0,
+            GPUREG_VERTEX_OFFSET | mask(0xF),
+        ]);
+        if triangles {
+            buf.extend_from_slice(&[
+                0x100,
+                GPUREG_GEOSTAGE_CONFIG | mask(0x2),
+                0x100,
+                GPUREG_GEOSTAGE_CONFIG2 | mask(0x2),
+            ]);
+        }
+        buf.extend_from_slice(&[
+            0,
+            GPUREG_START_DRAW_FUNC0 | mask(0x1),
+            1,
+            GPUREG_DRAWELEMENTS | mask(0xF),
+            1,
+            GPUREG_START_DRAW_FUNC0 | mask(0x1),
+        ]);
+        if triangles {
+            buf.extend_from_slice(&[
+                0,
+                GPUREG_GEOSTAGE_CONFIG | mask(0x2),
+                0,
+                GPUREG_GEOSTAGE_CONFIG2 | mask(0x2),
+            ]);
+        }
+        buf.extend_from_slice(&[1, GPUREG_VTX_FUNC | mask(0xF)]);
+    }
+}
@@ -1,10 +1,12 @@
-use ctru_sys::*;
+use super::regs::*;
 
 use super::{extra_params, mask, GpuCmd, CONSECUTIVE_WRITING};
+use crate::floater::f32x4tof24x4;
 
-///For immediate mode, use `Index(0xF)`
+///For immediate mode, use `Index(0xF)`.
+///Otherwise this is the index of a fixed attribute in the `attribute::Layout`, whose `Data` follows.
 #[derive(Clone,Copy)]
-pub(crate) struct Index(pub(crate) u32);
+pub struct Index(pub u32);
 
 impl GpuCmd for Index {
     type Out = [u32;2];
@@ -19,7 +21,7 @@ impl GpuCmd for Index {
 
 ///Obtain this data from `floater::f32x4tof24x4`
 #[derive(Clone,Copy)]
-pub(crate) struct Data(pub(crate) [u32;3]);
+pub struct Data(pub [u32;3]);
 
 impl GpuCmd for Data {
     type Out = [u32;4];
@@ -32,3 +34,18 @@ impl GpuCmd for Data {
         ]
     }
 }
+
+///Sets the value of fixed attribute `index`, see `attribute::Layout::add_fixed`.
+///XYZW order
+#[derive(Clone,Copy)]
+pub struct Value(pub u32, pub [f32;4]);
+
+impl GpuCmd for Value {
+    type Out = [u32;6];
+    fn cmd(self) -> Self::Out {
+        assert!(self.0 < 12, "There are only 12 attributes");
+        let [i0, i1] = Index(self.0).cmd();
+        let [d0, d1, d2, d3] = Data(f32x4tof24x4(self.1)).cmd();
+        [i0, i1, d0, d1, d2, d3]
+    }
+}
@@ -0,0 +1,163 @@
+//! Fog, blended over the texenv output by depth.
+//!
+//! ```rust,ignore
+//! Root + fog::Config { mode: fog::Mode::Fog, density_source: fog::DensitySource::Plain, z_flip: false }
+//!     + fog::Color(0x00C0C0C0)
+//!     + fog::Upload(&fog::Lut::exp2(0.05, 0.1, 100.0))
+//! ```
+//!
+//! Building a table doesn't touch the GPU, so it also works on the host.
+
+use super::regs::*;
+
+use super::{GpuCmd, GpuCmdByMut, mask, transfer::Transfer};
+
+///https://www.3dbrew.org/wiki/GPU/Internal_Registers#GPUREG_TEXENV_UPDATE_BUFFER
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u32)]
+pub enum Mode {
+    Disabled = 0,
+    Fog = 5,
+    ///Procedural gas, see `gas`. Needs `color_operation::FragOp::Gas`.
+    Gas = 7,
+}
+
+///Gas only
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u32)]
+pub enum DensitySource {
+    Plain,
+    Depth,
+}
+
+///Fog bits of `GPUREG_TEXENV_UPDATE_BUFFER`, the texenv buffer bits are left alone
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Config {
+    pub mode: Mode,
+    pub density_source: DensitySource,
+    ///Index the fog table with `1.0 - depth`
+    pub z_flip: bool,
+}
+
+impl GpuCmd for Config {
+    type Out = [u32; 2];
+    fn cmd(self) -> Self::Out {
+        [
+            (self.mode as u32) | (self.density_source as u32) << 3 | (self.z_flip as u32) << 16,
+            GPUREG_TEXENV_UPDATE_BUFFER | mask(0x5),
+        ]
+    }
+}
+
+///Note: `u32::from_le_bytes([r,g,b,0])`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Color(pub u32);
+
+impl GpuCmd for Color {
+    type Out = [u32; 2];
+    fn cmd(self) -> Self::Out {
+        [self.0 & 0xFFFFFF, GPUREG_FOG_COLOR | mask(0xF)]
+    }
+}
+
+///Packs an entry, `value` is clamped to `0.0..=1.0` and `delta` to `-2.0..2.0`
+pub fn encode_entry(value: f32, delta: f32) -> u32 {
+    let value = (value.clamp(0.0, 1.0) * 2048.0).min(2047.0) as u32;
+    let delta = (delta * 2048.0).clamp(-4096.0, 4095.0) as i32 as u32 & 0x1FFF;
+    (value << 13) | delta
+}
+
+///Inverse of `encode_entry`, up to rounding
+pub fn decode_entry(entry: u32) -> (f32, f32) {
+    let value = (entry >> 13) as f32 / 2048.0;
+    let delta = (((entry & 0x1FFF) << 19) as i32 >> 19) as f32 / 2048.0;
+    (value, delta)
+}
+
+///How much of the fragment is kept, `0.0` being all fog, indexed with depth in 128 steps
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Lut(pub [u32; 128]);
+
+impl Lut {
+    ///Samples `f(depth)` over `0.0..=1.0`
+    pub fn from_fn(f: impl Fn(f32) -> f32) -> Self {
+        Lut(std::array::from_fn(|i| {
+            let x = f(i as f32 / 128.0);
+            let next = f((i + 1) as f32 / 128.0);
+            encode_entry(x, next - x)
+        }))
+    }
+    ///Samples `f(distance)`, turning depth back into view space distance for a projection from `near` to `far`
+    pub fn from_distance_fn(near: f32, far: f32, f: impl Fn(f32) -> f32) -> Self {
+        Self::from_fn(|depth| f(far * near / (depth * (far - near) + near)))
+    }
+    ///No fog before `start`, all fog after `end`
+    pub fn linear(start: f32, end: f32, near: f32, far: f32) -> Self {
+        Self::from_distance_fn(near, far, |d| ((end - d) / (end - start)).clamp(0.0, 1.0))
+    }
+    ///`e^-(density * distance)`
+    pub fn exp(density: f32, near: f32, far: f32) -> Self {
+        Self::from_distance_fn(near, far, |d| (-density * d).exp())
+    }
+    ///`e^-(density * distance)^2`
+    pub fn exp2(density: f32, near: f32, far: f32) -> Self {
+        Self::from_distance_fn(near, far, |d| (-(density * d).powi(2)).exp())
+    }
+}
+
+///Uploads a table through `GPUREG_FOG_LUT_INDEX` and `FOG_LUT_DATA0`
+#[derive(Clone, Copy)]
+pub struct Upload<'a>(pub &'a Lut);
+
+impl<'a> GpuCmdByMut for Upload<'a> {
+    fn cmd_by_mut<A: std::alloc::Allocator>(self, buf: &mut Vec<u32, A>) {
+        buf.extend_from_slice(&[0, GPUREG_FOG_LUT_INDEX | mask(0xF)]);
+        //Every entry goes to FOG_LUT_DATA0, which advances the index by itself
+        Transfer {
+            reg: GPUREG_FOG_LUT_DATA0 | mask(0xF),
+            data: &self.0.0,
+        }
+        .cmd_by_mut(buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries() {
+        assert_eq!(encode_entry(0.0, 0.0), 0);
+        assert_eq!(encode_entry(0.5, 0.25), (0x400 << 13) | 0x200);
+        //Two's complement in 13 bits
+        assert_eq!(encode_entry(0.5, -0.25), (0x400 << 13) | 0x1E00);
+        assert_eq!(decode_entry((0x400 << 13) | 0x1E00), (0.5, -0.25));
+    }
+
+    #[test]
+    fn entries_round_trip() {
+        for value in [0.0, 0.125, 0.5, 2047.0 / 2048.0] {
+            for delta in [-2.0, -1.0, -0.5, -1.0 / 2048.0, 0.0, 1.0 / 2048.0, 0.75, 4095.0 / 2048.0] {
+                assert_eq!(decode_entry(encode_entry(value, delta)), (value, delta), "{value} {delta}");
+            }
+        }
+    }
+
+    #[test]
+    fn entries_clamp() {
+        //1.0 itself doesn't fit in 11 fractional bits
+        assert_eq!(encode_entry(1.0, 0.0), 0x7FF << 13);
+        assert_eq!(encode_entry(2.0, 3.0), (0x7FF << 13) | 0xFFF);
+        assert_eq!(encode_entry(-1.0, -3.0), 0x1000);
+        assert_eq!(decode_entry(0x1000), (0.0, -2.0));
+    }
+
+    #[test]
+    fn identity() {
+        let lut = Lut::from_fn(|x| x);
+        for (i, &entry) in lut.0.iter().enumerate() {
+            //Steps of 1/128 are 16 in both the value and the delta
+            assert_eq!(entry, ((i as u32 * 16) << 13) | 16, "entry {i}");
+        }
+    }
+}
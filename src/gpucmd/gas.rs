@@ -0,0 +1,171 @@
+//! Procedural gas.
+//!
+//! Gas is drawn in two passes: the first accumulates density into the gas buffer
+//! (`color_operation::FragOp::Gas`, `fog::Mode::Gas`), the second shades it with `Lut`,
+//! indexed by density or by how much light the gas receives.
+//! https://www.3dbrew.org/wiki/GPU/Internal_Registers#GPUREG_GAS_LIGHT_XY
+
+use super::regs::*;
+
+use super::{GpuCmd, GpuCmdByMut, mask, transfer::Transfer};
+use crate::floater::f32tof16;
+
+fn unorm8(x: f32) -> u32 {
+    (x.clamp(0.0, 1.0) * 255.0) as u32
+}
+
+///Attenuation of density when accumulating
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Attenuation(pub f32);
+
+impl GpuCmd for Attenuation {
+    type Out = [u32; 2];
+    fn cmd(self) -> Self::Out {
+        [f32tof16(self.0), GPUREG_GAS_ATTENUATION | mask(0xF)]
+    }
+}
+
+///Maximum accumulated density
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct AccMax(pub f32);
+
+impl GpuCmd for AccMax {
+    type Out = [u32; 2];
+    fn cmd(self) -> Self::Out {
+        [f32tof16(1.0 / self.0), GPUREG_GAS_ACCMAX | mask(0xF)]
+    }
+}
+
+///Lighting of the gas, all values are `0.0..=1.0`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct LightFactor {
+    pub min: f32,
+    pub max: f32,
+    pub attenuation: f32,
+}
+
+impl LightFactor {
+    fn param(self) -> u32 {
+        unorm8(self.min) | unorm8(self.max) << 8 | unorm8(self.attenuation) << 16
+    }
+}
+
+///Light from the XY plane
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Planar(pub LightFactor);
+
+impl GpuCmd for Planar {
+    type Out = [u32; 2];
+    fn cmd(self) -> Self::Out {
+        [self.0.param(), GPUREG_GAS_LIGHT_XY | mask(0xF)]
+    }
+}
+
+///Light along the view direction
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct View(pub LightFactor);
+
+impl GpuCmd for View {
+    type Out = [u32; 2];
+    fn cmd(self) -> Self::Out {
+        [self.0.param(), GPUREG_GAS_LIGHT_Z | mask(0xF)]
+    }
+}
+
+///Dot product of the view and light directions, `0.0..=1.0`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct LightDirection(pub f32);
+
+impl GpuCmd for LightDirection {
+    type Out = [u32; 2];
+    fn cmd(self) -> Self::Out {
+        [unorm8(self.0), GPUREG_GAS_LIGHT_Z_COLOR | mask(0x1)]
+    }
+}
+
+///What `Lut` is indexed with
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u32)]
+pub enum LutInput {
+    Density,
+    LightFactor,
+}
+
+impl GpuCmd for LutInput {
+    type Out = [u32; 2];
+    fn cmd(self) -> Self::Out {
+        [(self as u32) << 8, GPUREG_GAS_LIGHT_Z_COLOR | mask(0x2)]
+    }
+}
+
+///Depth difference per unit of density, `0.0..256.0`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DeltaZ(pub f32);
+
+impl GpuCmd for DeltaZ {
+    type Out = [u32; 2];
+    fn cmd(self) -> Self::Out {
+        [
+            ((self.0 * 256.0) as u32) & 0xFFFFFF,
+            GPUREG_GAS_DELTAZ_DEPTH | mask(0x7),
+        ]
+    }
+}
+
+///Which fragments accumulate density, compared against the depth buffer
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u32)]
+pub enum DepthFunction {
+    Never,
+    Always,
+    Greater,
+    Less,
+}
+
+impl GpuCmd for DepthFunction {
+    type Out = [u32; 2];
+    fn cmd(self) -> Self::Out {
+        [(self as u32) << 24, GPUREG_GAS_DELTAZ_DEPTH | mask(0x8)]
+    }
+}
+
+///8 colors, interpolated between, with the ninth only used for the last interpolation.
+///Note: colors are `u32::from_le_bytes([r,g,b,0])`
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Lut {
+    pub diffs: [u32; 8],
+    pub colors: [u32; 8],
+}
+
+impl Lut {
+    pub fn new(colors: [u32; 9]) -> Self {
+        Lut {
+            diffs: std::array::from_fn(|i| {
+                (0..3).fold(0, |acc, c| {
+                    let shift = c * 8;
+                    let d = ((colors[i + 1] >> shift) & 0xFF).wrapping_sub((colors[i] >> shift) & 0xFF);
+                    acc | (d & 0xFF) << shift
+                })
+            }),
+            colors: std::array::from_fn(|i| colors[i] & 0xFFFFFF),
+        }
+    }
+}
+
+///Uploads a table through `GPUREG_GAS_LUT_INDEX` and `GAS_LUT_DATA`, differences first
+#[derive(Clone, Copy)]
+pub struct Upload<'a>(pub &'a Lut);
+
+impl<'a> GpuCmdByMut for Upload<'a> {
+    fn cmd_by_mut<A: std::alloc::Allocator>(self, buf: &mut Vec<u32, A>) {
+        buf.extend_from_slice(&[0, GPUREG_GAS_LUT_INDEX | mask(0xF)]);
+        let mut data = [0; 16];
+        data[..8].copy_from_slice(&self.0.diffs);
+        data[8..].copy_from_slice(&self.0.colors);
+        Transfer {
+            reg: GPUREG_GAS_LUT_DATA | mask(0xF),
+            data,
+        }
+        .cmd_by_mut(buf);
+    }
+}
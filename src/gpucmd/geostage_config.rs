@@ -1,12 +1,12 @@
-use ctru_sys::*;
+use super::regs::*;
 
 use super::{mask, GpuCmd};
 
 #[derive(Clone,Copy)]
-pub(crate) struct Config {
-    pub(crate) geometry_shader_in_use: bool,
-    pub(crate) drawing_triangle_elements: bool,
-    pub(crate) use_reserved_geometry_shader_subdivision: bool
+pub struct Config {
+    pub geometry_shader_in_use: bool,
+    pub drawing_triangle_elements: bool,
+    pub use_reserved_geometry_shader_subdivision: bool
 }
 
 impl GpuCmd for Config {
@@ -0,0 +1,58 @@
+//! Immediate mode, sending vertices straight through the command buffer.
+//!
+//! ```rust,ignore
+//! Root + immediate::Draw {
+//!     mode: primitive::Mode::Triangles,
+//!     vertices: [[pos0, color0], [pos1, color1], [pos2, color2]],
+//! } + misc::FlushFramebuffer
+//! ```
+//!
+//! Flushing the framebuffer is left to the caller, as it only has to happen once after all draws.
+
+use super::regs::*;
+
+use super::{
+    GpuCmd, GpuCmdByMut,
+    fixed_attrib::{Data, Index},
+    mask,
+    primitive::Mode,
+};
+use crate::floater::f32x4tof24x4;
+
+///Each vertex is `N` attributes in XYZW order, in the order they were added to the `attribute::Layout`
+#[derive(Clone, Copy)]
+pub struct Draw<I> {
+    pub mode: Mode,
+    pub vertices: I,
+}
+
+impl<const N: usize, I: IntoIterator<Item = [[f32; 4]; N]>> GpuCmdByMut for Draw<I> {
+    fn cmd_by_mut<A: std::alloc::Allocator>(self, buf: &mut Vec<u32, A>) {
+        buf.extend_from_slice(&[
+            (self.mode as u32) << 8,
+            GPUREG_PRIMITIVE_CONFIG | mask(0x2),
+            1,
+            GPUREG_RESTART_PRIMITIVE | mask(0xF),
+            0x80000000,
+            GPUREG_INDEXBUFFER_CONFIG | mask(0xF),
+            1,
+            GPUREG_GEOSTAGE_CONFIG2 | mask(0x1),
+            0,
+            GPUREG_START_DRAW_FUNC0 | mask(0x1),
+        ]);
+        buf.extend_from_slice(&Index(0xF).cmd());
+        for vertex in self.vertices {
+            for attribute in vertex {
+                buf.extend_from_slice(&Data(f32x4tof24x4(attribute)).cmd());
+            }
+        }
+        buf.extend_from_slice(&[
+            1,
+            GPUREG_START_DRAW_FUNC0 | mask(0x1),
+            0,
+            GPUREG_GEOSTAGE_CONFIG2 | mask(0x1),
+            1,
+            GPUREG_VTX_FUNC | mask(0xF),
+        ]);
+    }
+}
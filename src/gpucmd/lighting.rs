@@ -0,0 +1,391 @@
+//! Fragment lighting.
+//!
+//! Lighting outputs `texenv::Source::FragmentPrimaryColor` (diffuse and ambient)
+//! and `texenv::Source::FragmentSecondaryColor` (specular),
+//! which have to be used by the texenv stages to show up at all.
+//! The vertex shader has to output `shader_outmap::NormQuatX..W` and `ViewX..Z` for it.
+//!
+//! ```rust,ignore
+//! Root + lighting::Enabled
+//!     + lighting::Ambient([0.1; 3])
+//!     + lighting::Config::default()
+//!     + lighting::Luts { d0: true, ..Default::default() }
+//!     + lighting::LutInputs::default()
+//!     + lighting::Lights(&[light])
+//! ```
+//!
+//! The lookup tables themselves are built and uploaded with `lighting_lut`.
+
+use super::regs::*;
+
+use super::{CONSECUTIVE_WRITING, GpuCmd, GpuCmdByMut, GpuCmdDisable, extra_params, mask};
+use crate::floater::{f32tof16, f32tof20, f32tofix13};
+
+///Subtract to Disable
+#[doc(alias = "Disable")]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Enabled;
+
+impl GpuCmd for Enabled {
+    type Out = [u32; 4];
+    fn cmd(self) -> Self::Out {
+        [
+            1,
+            GPUREG_LIGHTING_ENABLE0 | mask(0xF),
+            0,
+            GPUREG_LIGHTING_ENABLE1 | mask(0xF),
+        ]
+    }
+}
+
+impl GpuCmdDisable for Enabled {
+    type Out = [u32; 4];
+    fn cmd_disable(self) -> Self::Out {
+        [
+            0,
+            GPUREG_LIGHTING_ENABLE0 | mask(0xF),
+            1,
+            GPUREG_LIGHTING_ENABLE1 | mask(0xF),
+        ]
+    }
+}
+
+///RGB in `0.0..=1.0`, packed the way every lighting color register wants it
+pub fn color([r, g, b]: [f32; 3]) -> u32 {
+    let c = |x: f32| (x.clamp(0.0, 1.0) * 255.0).round() as u32;
+    (c(r) << 20) | (c(g) << 10) | c(b)
+}
+
+///Global ambient light, RGB
+#[derive(Clone, Copy, PartialEq)]
+pub struct Ambient(pub [f32; 3]);
+
+impl GpuCmd for Ambient {
+    type Out = [u32; 2];
+    fn cmd(self) -> Self::Out {
+        [color(self.0), GPUREG_LIGHTING_AMBIENT | mask(0xF)]
+    }
+}
+
+///https://www.3dbrew.org/wiki/GPU/Internal_Registers#GPUREG_LIGHTING_CONFIG0
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u32)]
+pub enum Fresnel {
+    None,
+    PrimaryAlpha,
+    SecondaryAlpha,
+    Both,
+}
+
+///Which lookup tables are available, `RB` and `RG` being the same as `RR` when they aren't.
+///More tables means more cycles per fragment.
+///https://www.3dbrew.org/wiki/GPU/Internal_Registers#GPUREG_LIGHTING_CONFIG0
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u32)]
+pub enum LayerConfig {
+    ///`D0`, `RR`, `SP`, `DA`
+    Config0 = 0,
+    ///`FR`, `RR`, `SP`, `DA`
+    Config1 = 1,
+    ///`D0`, `D1`, `RR`, `DA`
+    Config2 = 2,
+    ///`D0`, `D1`, `FR`, `DA`
+    Config3 = 3,
+    ///All but `FR`
+    Config4 = 4,
+    ///All but `D1`
+    Config5 = 5,
+    ///All but `RB` and `RG`
+    Config6 = 6,
+    ///All of them
+    Config7 = 8,
+}
+
+///Perturbs the normal with a texture
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Bump {
+    None,
+    ///The texture holds normals, in texture unit `0..=3`
+    NormalMap(u32),
+    ///The texture holds tangents, in texture unit `0..=3`
+    TangentMap(u32),
+}
+
+///Attenuates lights with a shadow texture
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Shadow {
+    ///Texture unit `0..=3`
+    pub texture_unit: u32,
+    pub primary: bool,
+    pub secondary: bool,
+    pub invert: bool,
+    ///Also attenuate the alpha
+    pub alpha: bool,
+}
+
+///`GPUREG_LIGHTING_CONFIG0`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Config {
+    pub shadow: Option<Shadow>,
+    pub fresnel: Fresnel,
+    pub layer_config: LayerConfig,
+    pub bump: Bump,
+    ///Recalculate the normal's Z after bump mapping
+    pub bump_renormalize: bool,
+    pub clamp_highlights: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            shadow: None,
+            fresnel: Fresnel::None,
+            layer_config: LayerConfig::Config0,
+            bump: Bump::None,
+            bump_renormalize: true,
+            clamp_highlights: true,
+        }
+    }
+}
+
+impl GpuCmd for Config {
+    type Out = [u32; 2];
+    fn cmd(self) -> Self::Out {
+        let shadow = self.shadow.map_or(0, |s| {
+            1 | (s.primary as u32) << 16
+                | (s.secondary as u32) << 17
+                | (s.invert as u32) << 18
+                | (s.alpha as u32) << 19
+                | (s.texture_unit & 3) << 24
+        });
+        let bump = match self.bump {
+            Bump::None => 0,
+            Bump::NormalMap(unit) => (unit & 3) << 22 | 1 << 28,
+            Bump::TangentMap(unit) => (unit & 3) << 22 | 2 << 28,
+        };
+        [
+            shadow
+                | (self.fresnel as u32) << 2
+                | (self.layer_config as u32) << 4
+                //Unknown, but always set by citro3d
+                | 4 << 8
+                | bump
+                | (self.clamp_highlights as u32) << 27
+                | (!self.bump_renormalize as u32) << 30
+                | 1 << 31,
+            GPUREG_LIGHTING_CONFIG0 | mask(0xF),
+        ]
+    }
+}
+
+///Which lookup tables and per light effects are used, `GPUREG_LIGHTING_CONFIG1`.
+///The per light masks have bit `i` for the light in slot `i`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Luts {
+    pub d0: bool,
+    pub d1: bool,
+    pub fr: bool,
+    pub rb: bool,
+    pub rg: bool,
+    pub rr: bool,
+    pub shadow: u8,
+    ///`SP` lookup table
+    pub spot: u8,
+    ///`DA` lookup table
+    pub distance_attenuation: u8,
+}
+
+impl GpuCmd for Luts {
+    type Out = [u32; 2];
+    fn cmd(self) -> Self::Out {
+        //The register has disable bits
+        let enabled = (self.shadow as u32)
+            | (self.spot as u32) << 8
+            | (self.d0 as u32) << 16
+            | (self.d1 as u32) << 17
+            | (self.fr as u32) << 19
+            | (self.rb as u32) << 20
+            | (self.rg as u32) << 21
+            | (self.rr as u32) << 22
+            | (self.distance_attenuation as u32) << 24;
+        [!enabled, GPUREG_LIGHTING_CONFIG1 | mask(0xF)]
+    }
+}
+
+///What a lookup table is indexed with, all are dot products of normalized vectors
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[repr(u32)]
+pub enum Input {
+    ///Normal and half vector
+    #[default]
+    NH,
+    ///View and half vector
+    VH,
+    ///Normal and view
+    NV,
+    ///Light and normal
+    LN,
+    ///Negated light and spotlight direction
+    SP,
+    ///Cosine of phi
+    CP,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[repr(u32)]
+pub enum Scale {
+    #[default]
+    X1 = 0,
+    X2 = 1,
+    X4 = 2,
+    X8 = 3,
+    X0_25 = 6,
+    X0_5 = 7,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct LutInput {
+    pub input: Input,
+    ///Takes the absolute value of the input, so the table covers `0.0..=1.0` instead of `-1.0..=1.0`
+    pub absolute: bool,
+    ///Applied to the looked up value
+    pub scale: Scale,
+}
+
+///`GPUREG_LIGHTING_LUTINPUT_ABS`, `SELECT` and `SCALE`
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct LutInputs {
+    pub d0: LutInput,
+    pub d1: LutInput,
+    pub sp: LutInput,
+    pub fr: LutInput,
+    pub rb: LutInput,
+    pub rg: LutInput,
+    pub rr: LutInput,
+}
+
+impl GpuCmd for LutInputs {
+    type Out = [u32; 4];
+    fn cmd(self) -> Self::Out {
+        let all = [self.d0, self.d1, self.sp, self.fr, self.rb, self.rg, self.rr];
+        let (mut abs, mut select, mut scale) = (0, 0, 0);
+        for (i, lut) in all.iter().enumerate() {
+            //The register has "not absolute" bits
+            abs |= (!lut.absolute as u32) << (i * 4 + 1);
+            select |= (lut.input as u32) << (i * 4);
+            scale |= (lut.scale as u32) << (i * 4);
+        }
+        [
+            abs,
+            GPUREG_LIGHTING_LUTINPUT_ABS | mask(0xF) | extra_params(2) | CONSECUTIVE_WRITING,
+            select,
+            scale,
+        ]
+    }
+}
+
+///One of the 8 lights
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Light {
+    ///RGB, all colors are already multiplied with the material's
+    pub specular0: [f32; 3],
+    pub specular1: [f32; 3],
+    pub diffuse: [f32; 3],
+    pub ambient: [f32; 3],
+    ///In view space
+    pub position: [f32; 3],
+    ///`position` is the direction towards the light instead
+    pub directional: bool,
+    ///Direction the spotlight points at, in view space, normalized
+    pub spot_direction: [f32; 3],
+    ///Light the back of surfaces too
+    pub two_side_diffuse: bool,
+    pub geometric_factor: [bool; 2],
+    ///The `DA` lookup table is indexed with `distance * attenuation_scale + attenuation_bias`
+    pub attenuation_bias: f32,
+    pub attenuation_scale: f32,
+}
+
+impl Default for Light {
+    fn default() -> Self {
+        Light {
+            specular0: [0.0; 3],
+            specular1: [0.0; 3],
+            diffuse: [1.0; 3],
+            ambient: [0.0; 3],
+            position: [0.0, 0.0, 1.0],
+            directional: true,
+            spot_direction: [0.0, 0.0, -1.0],
+            two_side_diffuse: false,
+            geometric_factor: [false; 2],
+            attenuation_bias: 0.0,
+            attenuation_scale: 1.0,
+        }
+    }
+}
+
+impl Light {
+    ///Makes the `DA` lookup table go from `start` (index 0.0) to `end` (index 1.0)
+    pub fn attenuation_range(self, start: f32, end: f32) -> Self {
+        let scale = 1.0 / (end - start);
+        Light {
+            attenuation_scale: scale,
+            attenuation_bias: -start * scale,
+            ..self
+        }
+    }
+    ///`GPUREG_LIGHTi_SPECULAR0..=ATTENUATION_SCALE`, in one consecutive write
+    pub fn params(&self) -> [u32; 12] {
+        let [x, y, z] = self.position.map(f32tof16);
+        //The hardware wants the direction towards the spotlight
+        let [sx, sy, sz] = self.spot_direction.map(|d| f32tofix13(-d));
+        [
+            color(self.specular0),
+            color(self.specular1),
+            color(self.diffuse),
+            color(self.ambient),
+            x | y << 16,
+            z,
+            sx | sy << 16,
+            sz,
+            0,
+            (self.directional as u32)
+                | (self.two_side_diffuse as u32) << 1
+                | (self.geometric_factor[0] as u32) << 2
+                | (self.geometric_factor[1] as u32) << 3,
+            f32tof20(self.attenuation_bias),
+            f32tof20(self.attenuation_scale),
+        ]
+    }
+}
+
+///Writes `Light`s into light slots `0..n`, sets the number of lights and the permutation mapping slot `i` to light `i`
+#[derive(Clone, Copy)]
+pub struct Lights<'a>(pub &'a [Light]);
+
+impl<'a> GpuCmdByMut for Lights<'a> {
+    fn cmd_by_mut<A: std::alloc::Allocator>(self, buf: &mut Vec<u32, A>) {
+        let n = self.0.len() as u32;
+        assert!((1..=8).contains(&n), "There are 1 to 8 lights");
+        for (i, light) in self.0.iter().enumerate() {
+            let [first, rest @ ..] = light.params();
+            buf.extend_from_slice(&[
+                first,
+                (GPUREG_LIGHT0_SPECULAR0 + i as u32 * 0x10)
+                    | mask(0xF)
+                    | extra_params(11)
+                    | CONSECUTIVE_WRITING,
+            ]);
+            buf.extend_from_slice(&rest);
+            //11 extra params, pad to 8 bytes
+            buf.push(0);
+        }
+        let permutation = (0..8).fold(0, |acc, i| acc | i << (i * 4));
+        buf.extend_from_slice(&[
+            n - 1,
+            GPUREG_LIGHTING_NUM_LIGHTS | mask(0xF),
+            permutation,
+            GPUREG_LIGHTING_LIGHT_PERMUTATION | mask(0xF),
+        ]);
+    }
+}
@@ -0,0 +1,160 @@
+//! Lighting lookup tables.
+//!
+//! Each table has 256 entries of a 12 bit unsigned value in `0.0..1.0`
+//! and a 12 bit delta to the next entry (a sign bit and 11 bits in `0.0..1.0`), which the hardware interpolates with.
+//! Tables indexed with an absolute input cover `0.0..=1.0`,
+//! the others cover `-1.0..=1.0` with the negative half in entries `128..=255` (two's complement).
+//!
+//! ```rust,ignore
+//! Root + lighting_lut::Upload(Id::D0, &Lut::phong(30.0))
+//! ```
+//!
+//! Building a table doesn't touch the GPU, so it also works on the host.
+
+use super::regs::*;
+
+use super::{GpuCmdByMut, mask, transfer::Transfer};
+
+///https://www.3dbrew.org/wiki/GPU/Internal_Registers#GPUREG_LIGHTING_LUT_INDEX
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Id {
+    D0,
+    D1,
+    FR,
+    RB,
+    RG,
+    RR,
+    ///Spotlight of light `0..=7`
+    SP(u8),
+    ///Distance attenuation of light `0..=7`
+    DA(u8),
+}
+
+impl Id {
+    pub fn index(self) -> u32 {
+        match self {
+            Id::D0 => 0,
+            Id::D1 => 1,
+            Id::FR => 3,
+            Id::RB => 4,
+            Id::RG => 5,
+            Id::RR => 6,
+            Id::SP(light) => 8 + (light as u32 & 7),
+            Id::DA(light) => 16 + (light as u32 & 7),
+        }
+    }
+}
+
+///Packs an entry, `value` is clamped to `0.0..=1.0` and `delta` to `-1.0..=1.0`
+pub fn encode_entry(value: f32, delta: f32) -> u32 {
+    let value = (value.clamp(0.0, 1.0) * 4096.0).min(4095.0) as u32;
+    let sign = if delta < 0.0 { 0x800 } else { 0 };
+    let delta = (delta.abs() * 2048.0).min(2047.0) as u32;
+    value | ((sign | delta) << 12)
+}
+
+///Inverse of `encode_entry`, up to rounding
+pub fn decode_entry(entry: u32) -> (f32, f32) {
+    let value = (entry & 0xFFF) as f32 / 4096.0;
+    let delta = ((entry >> 12) & 0x7FF) as f32 / 2048.0;
+    let delta = if entry & (0x800 << 12) != 0 { -delta } else { delta };
+    (value, delta)
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Lut(pub [u32; 256]);
+
+impl Lut {
+    ///Entry `i` is `values[i]`, interpolating towards `values[i] + deltas[i]`
+    pub fn from_values(values: &[f32; 256], deltas: &[f32; 256]) -> Self {
+        Lut(std::array::from_fn(|i| encode_entry(values[i], deltas[i])))
+    }
+    ///Samples `f` over `0.0..=1.0`, or over `-1.0..=1.0` if `negative`, for an input that isn't absolute
+    pub fn from_fn(f: impl Fn(f32) -> f32, negative: bool) -> Self {
+        let (min, step) = if negative { (-128, 1.0 / 128.0) } else { (0, 1.0 / 256.0) };
+        let mut values = [0.0; 256];
+        let mut deltas = [0.0; 256];
+        for i in min..min + 256 {
+            let x = f(i as f32 * step);
+            let next = f((i + 1) as f32 * step);
+            values[(i & 0xFF) as usize] = x;
+            deltas[(i & 0xFF) as usize] = next - x;
+        }
+        Self::from_values(&values, &deltas)
+    }
+    ///`x^shininess`, for `D0` or `D1` with `Input::NH`
+    pub fn phong(shininess: f32) -> Self {
+        Self::from_fn(|x| x.max(0.0).powf(shininess), false)
+    }
+    ///A hard edged spotlight cone with a half angle of `angle` radians, for `SP`
+    pub fn spotlight(angle: f32) -> Self {
+        let cutoff = angle.cos();
+        Self::from_fn(|x| if x >= cutoff { 1.0 } else { 0.0 }, true)
+    }
+    ///Samples `f(distance)` for `DA`, from `start` to `end`.
+    ///The light needs `Light::attenuation_range(start, end)` to match.
+    pub fn distance_attenuation(start: f32, end: f32, f: impl Fn(f32) -> f32) -> Self {
+        Self::from_fn(|x| f(start + x * (end - start)), false)
+    }
+    ///`1 / (constant + linear * d + quadratic * d^2)`, see `distance_attenuation`
+    pub fn quadratic(start: f32, end: f32, constant: f32, linear: f32, quadratic: f32) -> Self {
+        Self::distance_attenuation(start, end, |d| {
+            1.0 / (constant + linear * d + quadratic * d * d)
+        })
+    }
+}
+
+///Uploads a table through `GPUREG_LIGHTING_LUT_INDEX` and `LUT_DATA0`
+#[derive(Clone, Copy)]
+pub struct Upload<'a>(pub Id, pub &'a Lut);
+
+impl<'a> GpuCmdByMut for Upload<'a> {
+    fn cmd_by_mut<A: std::alloc::Allocator>(self, buf: &mut Vec<u32, A>) {
+        buf.extend_from_slice(&[self.0.index() << 8, GPUREG_LIGHTING_LUT_INDEX | mask(0xF)]);
+        //Every entry goes to LUT_DATA0, which advances the index by itself
+        Transfer {
+            reg: GPUREG_LIGHTING_LUT_DATA0 | mask(0xF),
+            data: &self.1.0,
+        }
+        .cmd_by_mut(buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries() {
+        assert_eq!(encode_entry(0.0, 0.0), 0);
+        assert_eq!(encode_entry(1.0, 0.0), 0xFFF);
+        assert_eq!(encode_entry(0.5, 0.25), 0x800 | (0x200 << 12));
+        assert_eq!(encode_entry(0.5, -0.25), 0x800 | ((0x800 | 0x200) << 12));
+        assert_eq!(decode_entry(0x800 | ((0x800 | 0x200) << 12)), (0.5, -0.25));
+    }
+
+    #[test]
+    fn entries_clamp() {
+        assert_eq!(encode_entry(2.0, 3.0), 0xFFF | (0x7FF << 12));
+        assert_eq!(encode_entry(-1.0, -3.0), 0xFFF << 12);
+    }
+
+    #[test]
+    fn identity() {
+        let lut = Lut::from_fn(|x| x, false);
+        for (i, &entry) in lut.0.iter().enumerate() {
+            //Steps of 1/256 are 16 in the value and 8 in the delta
+            assert_eq!(entry, (i as u32 * 16) | (8 << 12), "entry {i}");
+        }
+    }
+
+    #[test]
+    fn identity_negative() {
+        let lut = Lut::from_fn(|x| x, true);
+        assert_eq!(lut.0[1], 32 | (16 << 12));
+        assert_eq!(lut.0[127], (127 * 32) | (16 << 12));
+        //Negative inputs clamp to 0, but still step up towards the next entry
+        assert_eq!(lut.0[128], 16 << 12);
+        assert_eq!(lut.0[255], 16 << 12);
+    }
+}
@@ -1,4 +1,4 @@
-use ctru_sys::*;
+use super::regs::*;
 use super::{mask, GpuCmd};
 
 #[derive(Clone, Copy,PartialEq, Eq, Debug)]
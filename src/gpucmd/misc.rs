@@ -1,9 +1,9 @@
-use ctru_sys::*;
+use super::regs::*;
 
 use super::{mask, GpuCmd};
 
 #[derive(Clone,Copy)]
-pub(crate) struct NumAttr(pub(crate) u32);
+pub struct NumAttr(pub u32);
 
 impl GpuCmd for NumAttr {
     type Out = [u32;2];
@@ -17,7 +17,7 @@ impl GpuCmd for NumAttr {
 }
 
 #[derive(Clone,Copy)]
-pub(crate) struct NumVertices(pub(crate) u32);
+pub struct NumVertices(pub u32);
 
 impl GpuCmd for NumVertices {
     type Out = [u32;2];
@@ -31,7 +31,7 @@ impl GpuCmd for NumVertices {
 }
 
 #[derive(Clone,Copy)]
-pub(crate) struct DrawingMode;
+pub struct DrawingMode;
 
 impl GpuCmd for DrawingMode {
     type Out = [u32;2];
@@ -45,7 +45,7 @@ impl GpuCmd for DrawingMode {
 }
 
 #[derive(Clone,Copy)]
-pub(crate) struct ConfigurationMode;
+pub struct ConfigurationMode;
 
 impl GpuCmd for ConfigurationMode {
     type Out = [u32;2];
@@ -59,7 +59,7 @@ impl GpuCmd for ConfigurationMode {
 }
 
 #[derive(Clone,Copy)]
-pub(crate) struct ClearPostVertexCache;
+pub struct ClearPostVertexCache;
 
 impl GpuCmd for ClearPostVertexCache {
     type Out = [u32;2];
@@ -73,7 +73,7 @@ impl GpuCmd for ClearPostVertexCache {
 }
 
 #[derive(Clone,Copy)]
-pub(crate) struct FlushFramebuffer;
+pub struct FlushFramebuffer;
 
 impl GpuCmd for FlushFramebuffer {
     type Out = [u32;2];
@@ -87,7 +87,7 @@ impl GpuCmd for FlushFramebuffer {
 }
 
 #[derive(Clone,Copy)]
-pub(crate) struct VshEntrypoint(pub(crate) u32);
+pub struct VshEntrypoint(pub u32);
 
 impl GpuCmd for VshEntrypoint {
     type Out = [u32;2];
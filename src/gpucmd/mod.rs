@@ -11,10 +11,23 @@ pub mod chain;
 pub mod geostage_config;
 pub mod primitive;
 pub mod fixed_attrib;
+pub mod texunit;
+pub mod attribute;
+pub mod draw;
+pub mod vertex;
+pub mod immediate;
+pub mod lighting;
+pub mod lighting_lut;
+pub mod fog;
+pub mod gas;
+pub mod texenv_pipeline;
 pub mod misc;
+pub mod clip_plane;
+pub mod regs;
 
 use std::alloc::Allocator;
 
+#[cfg(target_os = "horizon")]
 use ctru::linear::LinearAllocator;
 
 ///Note: The Buffer **MUST** be `0x10` bytes aligned!
@@ -29,6 +42,7 @@ pub struct CommandEncoder<A> where A:Allocator {
     buf: CommandBuffer<A>
 }
 
+#[cfg(target_os = "horizon")]
 impl CommandEncoder<CmdBufAllocator> {
     //TODO: Make `try` versions
     pub fn new() -> CommandEncoder<CmdBufAllocator> {
@@ -47,9 +61,23 @@ impl CommandEncoder<CmdBufAllocator> {
     }
 }
 
+impl<A:Allocator> CommandEncoder<A> {
+    ///For command buffers that never reach the GPU, like ones run by `reference::Renderer` on the host
+    pub fn new_in(alloc: A) -> CommandEncoder<A> {
+        CommandEncoder {
+            buf: CommandBuffer {
+                buf: Vec::new_in(alloc)
+            }
+        }
+    }
+}
+
+///Allocates command buffers in linear memory, where the GPU can read them
+#[cfg(target_os = "horizon")]
 #[derive(Clone, Copy)]
 pub struct CmdBufAllocator;
 
+#[cfg(target_os = "horizon")]
 unsafe impl Allocator for CmdBufAllocator {
     fn allocate(
         &self,
@@ -123,7 +151,7 @@ pub struct Finish;
 impl<A:Allocator> std::ops::Add<Finish> for CommandEncoder<A> {
     type Output = CommandBuffer<A>;
     fn add(self, rhs: Finish) -> Self::Output {
-        use ctru_sys::*;
+        use regs::*;
         let mut buf = self.buf;
         buf.buf.extend_from_slice(&[0x12345678,GPUREG_FINALIZE | mask(0xF)]);
         buf
@@ -1,8 +1,8 @@
-use ctru_sys::*;
+use super::regs::*;
 
 use super::{GpuCmd,mask};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 #[repr(u32)]
 pub enum Mode {
     Triangles,
@@ -12,9 +12,9 @@ pub enum Mode {
 }
 
 #[derive(Clone,Copy)]
-pub(crate) struct Config {
-    pub(crate) outmap_total_minus_1: u32,
-    pub(crate) primitive_mode: Mode
+pub struct Config {
+    pub outmap_total_minus_1: u32,
+    pub primitive_mode: Mode
 }
 
 impl GpuCmd for Config {
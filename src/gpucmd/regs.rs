@@ -0,0 +1,167 @@
+//! PICA register ids, copied from libctru's `gpu_regs.h` so encoding commands doesn't need ctru.
+//!
+//! https://www.3dbrew.org/wiki/GPU/Internal_Registers
+
+pub const GPUREG_FINALIZE: u32 = 0x0010;
+pub const GPUREG_FACECULLING_CONFIG: u32 = 0x0040;
+pub const GPUREG_VIEWPORT_WIDTH: u32 = 0x0041;
+pub const GPUREG_VIEWPORT_INVW: u32 = 0x0042;
+pub const GPUREG_VIEWPORT_HEIGHT: u32 = 0x0043;
+pub const GPUREG_VIEWPORT_INVH: u32 = 0x0044;
+pub const GPUREG_FRAGOP_CLIP: u32 = 0x0047;
+pub const GPUREG_FRAGOP_CLIP_DATA0: u32 = 0x0048;
+pub const GPUREG_FRAGOP_CLIP_DATA1: u32 = 0x0049;
+pub const GPUREG_FRAGOP_CLIP_DATA2: u32 = 0x004A;
+pub const GPUREG_FRAGOP_CLIP_DATA3: u32 = 0x004B;
+pub const GPUREG_DEPTHMAP_SCALE: u32 = 0x004D;
+pub const GPUREG_DEPTHMAP_OFFSET: u32 = 0x004E;
+pub const GPUREG_SH_OUTMAP_TOTAL: u32 = 0x004F;
+pub const GPUREG_SH_OUTMAP_O0: u32 = 0x0050;
+pub const GPUREG_EARLYDEPTH_FUNC: u32 = 0x0061;
+pub const GPUREG_EARLYDEPTH_TEST1: u32 = 0x0062;
+pub const GPUREG_EARLYDEPTH_CLEAR: u32 = 0x0063;
+pub const GPUREG_SH_OUTATTR_MODE: u32 = 0x0064;
+pub const GPUREG_SCISSORTEST_MODE: u32 = 0x0065;
+pub const GPUREG_SCISSORTEST_POS: u32 = 0x0066;
+pub const GPUREG_SCISSORTEST_DIM: u32 = 0x0067;
+pub const GPUREG_VIEWPORT_XY: u32 = 0x0068;
+pub const GPUREG_EARLYDEPTH_DATA: u32 = 0x006A;
+pub const GPUREG_DEPTHMAP_ENABLE: u32 = 0x006D;
+pub const GPUREG_RENDERBUF_DIM: u32 = 0x006E;
+pub const GPUREG_SH_OUTATTR_CLOCK: u32 = 0x006F;
+pub const GPUREG_TEXUNIT_CONFIG: u32 = 0x0080;
+pub const GPUREG_TEXUNIT0_BORDER_COLOR: u32 = 0x0081;
+pub const GPUREG_TEXUNIT0_DIM: u32 = 0x0082;
+pub const GPUREG_TEXUNIT0_PARAM: u32 = 0x0083;
+pub const GPUREG_TEXUNIT0_LOD: u32 = 0x0084;
+pub const GPUREG_TEXUNIT0_ADDR1: u32 = 0x0085;
+pub const GPUREG_TEXUNIT0_ADDR2: u32 = 0x0086;
+pub const GPUREG_TEXUNIT0_ADDR3: u32 = 0x0087;
+pub const GPUREG_TEXUNIT0_ADDR4: u32 = 0x0088;
+pub const GPUREG_TEXUNIT0_ADDR5: u32 = 0x0089;
+pub const GPUREG_TEXUNIT0_ADDR6: u32 = 0x008A;
+pub const GPUREG_TEXUNIT0_SHADOW: u32 = 0x008B;
+pub const GPUREG_TEXUNIT0_TYPE: u32 = 0x008E;
+pub const GPUREG_LIGHTING_ENABLE0: u32 = 0x008F;
+pub const GPUREG_TEXUNIT1_BORDER_COLOR: u32 = 0x0091;
+pub const GPUREG_TEXUNIT1_DIM: u32 = 0x0092;
+pub const GPUREG_TEXUNIT1_PARAM: u32 = 0x0093;
+pub const GPUREG_TEXUNIT1_LOD: u32 = 0x0094;
+pub const GPUREG_TEXUNIT1_ADDR: u32 = 0x0095;
+pub const GPUREG_TEXUNIT1_TYPE: u32 = 0x0096;
+pub const GPUREG_TEXUNIT2_BORDER_COLOR: u32 = 0x0099;
+pub const GPUREG_TEXUNIT2_DIM: u32 = 0x009A;
+pub const GPUREG_TEXUNIT2_PARAM: u32 = 0x009B;
+pub const GPUREG_TEXUNIT2_LOD: u32 = 0x009C;
+pub const GPUREG_TEXUNIT2_ADDR: u32 = 0x009D;
+pub const GPUREG_TEXUNIT2_TYPE: u32 = 0x009E;
+pub const GPUREG_TEXENV0_SOURCE: u32 = 0x00C0;
+pub const GPUREG_TEXENV1_SOURCE: u32 = 0x00C8;
+pub const GPUREG_TEXENV2_SOURCE: u32 = 0x00D0;
+pub const GPUREG_TEXENV3_SOURCE: u32 = 0x00D8;
+pub const GPUREG_TEXENV_UPDATE_BUFFER: u32 = 0x00E0;
+pub const GPUREG_FOG_COLOR: u32 = 0x00E1;
+pub const GPUREG_GAS_ATTENUATION: u32 = 0x00E4;
+pub const GPUREG_GAS_ACCMAX: u32 = 0x00E5;
+pub const GPUREG_FOG_LUT_INDEX: u32 = 0x00E6;
+pub const GPUREG_FOG_LUT_DATA0: u32 = 0x00E8;
+pub const GPUREG_TEXENV4_SOURCE: u32 = 0x00F0;
+pub const GPUREG_TEXENV5_SOURCE: u32 = 0x00F8;
+pub const GPUREG_TEXENV_BUFFER_COLOR: u32 = 0x00FD;
+pub const GPUREG_COLOR_OPERATION: u32 = 0x0100;
+pub const GPUREG_BLEND_FUNC: u32 = 0x0101;
+pub const GPUREG_LOGIC_OP: u32 = 0x0102;
+pub const GPUREG_BLEND_COLOR: u32 = 0x0103;
+pub const GPUREG_FRAGOP_ALPHA_TEST: u32 = 0x0104;
+pub const GPUREG_STENCIL_TEST: u32 = 0x0105;
+pub const GPUREG_STENCIL_OP: u32 = 0x0106;
+pub const GPUREG_DEPTH_COLOR_MASK: u32 = 0x0107;
+pub const GPUREG_FRAMEBUFFER_INVALIDATE: u32 = 0x0110;
+pub const GPUREG_FRAMEBUFFER_FLUSH: u32 = 0x0111;
+pub const GPUREG_COLORBUFFER_READ: u32 = 0x0112;
+pub const GPUREG_COLORBUFFER_WRITE: u32 = 0x0113;
+pub const GPUREG_DEPTHBUFFER_READ: u32 = 0x0114;
+pub const GPUREG_DEPTHBUFFER_WRITE: u32 = 0x0115;
+pub const GPUREG_DEPTHBUFFER_FORMAT: u32 = 0x0116;
+pub const GPUREG_COLORBUFFER_FORMAT: u32 = 0x0117;
+pub const GPUREG_EARLYDEPTH_TEST2: u32 = 0x0118;
+pub const GPUREG_FRAMEBUFFER_BLOCK32: u32 = 0x011B;
+pub const GPUREG_DEPTHBUFFER_LOC: u32 = 0x011C;
+pub const GPUREG_COLORBUFFER_LOC: u32 = 0x011D;
+pub const GPUREG_FRAMEBUFFER_DIM: u32 = 0x011E;
+pub const GPUREG_GAS_LIGHT_XY: u32 = 0x0120;
+pub const GPUREG_GAS_LIGHT_Z: u32 = 0x0121;
+pub const GPUREG_GAS_LIGHT_Z_COLOR: u32 = 0x0122;
+pub const GPUREG_GAS_LUT_INDEX: u32 = 0x0123;
+pub const GPUREG_GAS_LUT_DATA: u32 = 0x0124;
+pub const GPUREG_GAS_DELTAZ_DEPTH: u32 = 0x0126;
+pub const GPUREG_FRAGOP_SHADOW: u32 = 0x0130;
+pub const GPUREG_LIGHT0_SPECULAR0: u32 = 0x0140;
+pub const GPUREG_LIGHT0_SPECULAR1: u32 = 0x0141;
+pub const GPUREG_LIGHT0_DIFFUSE: u32 = 0x0142;
+pub const GPUREG_LIGHT0_AMBIENT: u32 = 0x0143;
+pub const GPUREG_LIGHT0_XY: u32 = 0x0144;
+pub const GPUREG_LIGHT0_Z: u32 = 0x0145;
+pub const GPUREG_LIGHT0_SPOTDIR_XY: u32 = 0x0146;
+pub const GPUREG_LIGHT0_SPOTDIR_Z: u32 = 0x0147;
+pub const GPUREG_LIGHT0_CONFIG: u32 = 0x0149;
+pub const GPUREG_LIGHT0_ATTENUATION_BIAS: u32 = 0x014A;
+pub const GPUREG_LIGHT0_ATTENUATION_SCALE: u32 = 0x014B;
+pub const GPUREG_LIGHTING_AMBIENT: u32 = 0x01C0;
+pub const GPUREG_LIGHTING_NUM_LIGHTS: u32 = 0x01C2;
+pub const GPUREG_LIGHTING_CONFIG0: u32 = 0x01C3;
+pub const GPUREG_LIGHTING_CONFIG1: u32 = 0x01C4;
+pub const GPUREG_LIGHTING_LUT_INDEX: u32 = 0x01C5;
+pub const GPUREG_LIGHTING_ENABLE1: u32 = 0x01C6;
+pub const GPUREG_LIGHTING_LUT_DATA0: u32 = 0x01C8;
+pub const GPUREG_LIGHTING_LUTINPUT_ABS: u32 = 0x01D0;
+pub const GPUREG_LIGHTING_LUTINPUT_SELECT: u32 = 0x01D1;
+pub const GPUREG_LIGHTING_LUTINPUT_SCALE: u32 = 0x01D2;
+pub const GPUREG_LIGHTING_LIGHT_PERMUTATION: u32 = 0x01D9;
+pub const GPUREG_ATTRIBBUFFERS_LOC: u32 = 0x0200;
+pub const GPUREG_ATTRIBBUFFERS_FORMAT_LOW: u32 = 0x0201;
+pub const GPUREG_ATTRIBBUFFERS_FORMAT_HIGH: u32 = 0x0202;
+pub const GPUREG_ATTRIBBUFFER0_OFFSET: u32 = 0x0203;
+pub const GPUREG_ATTRIBBUFFER0_CONFIG1: u32 = 0x0204;
+pub const GPUREG_ATTRIBBUFFER0_CONFIG2: u32 = 0x0205;
+pub const GPUREG_INDEXBUFFER_CONFIG: u32 = 0x0227;
+pub const GPUREG_NUMVERTICES: u32 = 0x0228;
+pub const GPUREG_GEOSTAGE_CONFIG: u32 = 0x0229;
+pub const GPUREG_VERTEX_OFFSET: u32 = 0x022A;
+pub const GPUREG_POST_VERTEX_CACHE_NUM: u32 = 0x022D;
+pub const GPUREG_DRAWARRAYS: u32 = 0x022E;
+pub const GPUREG_DRAWELEMENTS: u32 = 0x022F;
+pub const GPUREG_VTX_FUNC: u32 = 0x0231;
+pub const GPUREG_FIXEDATTRIB_INDEX: u32 = 0x0232;
+pub const GPUREG_FIXEDATTRIB_DATA0: u32 = 0x0233;
+pub const GPUREG_FIXEDATTRIB_DATA1: u32 = 0x0234;
+pub const GPUREG_FIXEDATTRIB_DATA2: u32 = 0x0235;
+pub const GPUREG_CMDBUF_SIZE0: u32 = 0x0238;
+pub const GPUREG_CMDBUF_ADDR0: u32 = 0x023A;
+pub const GPUREG_CMDBUF_JUMP0: u32 = 0x023C;
+pub const GPUREG_VSH_NUM_ATTR: u32 = 0x0242;
+pub const GPUREG_VSH_COM_MODE: u32 = 0x0244;
+pub const GPUREG_START_DRAW_FUNC0: u32 = 0x0245;
+pub const GPUREG_VSH_OUTMAP_TOTAL1: u32 = 0x024A;
+pub const GPUREG_VSH_OUTMAP_TOTAL2: u32 = 0x0251;
+pub const GPUREG_GSH_MISC0: u32 = 0x0252;
+pub const GPUREG_GEOSTAGE_CONFIG2: u32 = 0x0253;
+pub const GPUREG_GSH_MISC1: u32 = 0x0254;
+pub const GPUREG_PRIMITIVE_CONFIG: u32 = 0x025E;
+pub const GPUREG_RESTART_PRIMITIVE: u32 = 0x025F;
+pub const GPUREG_GSH_BOOLUNIFORM: u32 = 0x0280;
+pub const GPUREG_GSH_ENTRYPOINT: u32 = 0x028A;
+pub const GPUREG_VSH_BOOLUNIFORM: u32 = 0x02B0;
+pub const GPUREG_VSH_INTUNIFORM_I0: u32 = 0x02B1;
+pub const GPUREG_VSH_INPUTBUFFER_CONFIG: u32 = 0x02B9;
+pub const GPUREG_VSH_ENTRYPOINT: u32 = 0x02BA;
+pub const GPUREG_VSH_ATTRIBUTES_PERMUTATION_LOW: u32 = 0x02BB;
+pub const GPUREG_VSH_ATTRIBUTES_PERMUTATION_HIGH: u32 = 0x02BC;
+pub const GPUREG_VSH_OUTMAP_MASK: u32 = 0x02BD;
+pub const GPUREG_VSH_CODETRANSFER_END: u32 = 0x02BF;
+pub const GPUREG_VSH_FLOATUNIFORM_CONFIG: u32 = 0x02C0;
+pub const GPUREG_VSH_FLOATUNIFORM_DATA: u32 = 0x02C1;
+pub const GPUREG_VSH_CODETRANSFER_CONFIG: u32 = 0x02CB;
+pub const GPUREG_VSH_CODETRANSFER_DATA: u32 = 0x02CC;
+pub const GPUREG_VSH_OPDESCS_CONFIG: u32 = 0x02D5;
+pub const GPUREG_VSH_OPDESCS_DATA: u32 = 0x02D6;
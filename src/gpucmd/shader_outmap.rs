@@ -1,4 +1,4 @@
-use ctru_sys::*;
+use super::regs::*;
 
 #[derive(Clone, Copy)]
 #[repr(u8)]
@@ -20,12 +20,12 @@ pub enum Component {
     TexCoord1U,
     TexCoord1V,
     TexCoord0W,
-    ViewX,
+    ViewX = 0x12,
     ViewY,
     ViewZ,
-    TexCoord2U,
+    TexCoord2U = 0x16,
     TexCoord2V,
-    Unused,
+    Unused = 0x1F,
 }
 
 pub use Component::*;
@@ -34,10 +34,10 @@ use super::{mask, GpuCmd, GpuCmdDisable, GpuCmdByMut, Root};
 
 ///https://www.3dbrew.org/wiki/GPU/Internal_Registers#GPUREG_SH_OUTMAP_Oi
 #[derive(Clone, Copy)]
-pub(crate) struct OutMap(pub(crate) u32, pub(crate) Component, pub(crate) Component, pub(crate) Component, pub(crate) Component);
+pub struct OutMap(pub u32, pub Component, pub Component, pub Component, pub Component);
 ///https://www.3dbrew.org/wiki/GPU/Internal_Registers#GPUREG_SH_OUTMAP_TOTAL
 #[derive(Clone, Copy)]
-pub(crate) struct OutMapTotal(pub(crate) u32);
+pub struct OutMapTotal(pub u32);
 
 pub(crate) fn unused(reg: u32) -> OutMap {
     OutMap(reg, Unused, Unused, Unused, Unused)
@@ -74,7 +74,7 @@ impl GpuCmd for OutMap {
 }
 
 #[derive(Clone,Copy)]
-pub(crate) struct UseTextureCoordinates;
+pub struct UseTextureCoordinates;
 
 impl GpuCmd for UseTextureCoordinates {
     type Out = [u32;2];
@@ -93,14 +93,14 @@ impl GpuCmdDisable for UseTextureCoordinates {
 }
 
 #[derive(Clone,Copy)]
-pub(crate) struct Clock {
-    pub(crate) position_z: bool,
-    pub(crate) color: bool,
-    pub(crate) texcoord0: bool,
-    pub(crate) texcoord1: bool,
-    pub(crate) texcoord2: bool,
-    pub(crate) texcoord0w: bool,
-    pub(crate) normquat_or_view: bool,
+pub struct Clock {
+    pub position_z: bool,
+    pub color: bool,
+    pub texcoord0: bool,
+    pub texcoord1: bool,
+    pub texcoord2: bool,
+    pub texcoord0w: bool,
+    pub normquat_or_view: bool,
 }
 
 impl GpuCmd for Clock {
@@ -1,8 +1,8 @@
-use ctru_sys::*;
+use super::regs::*;
 use std::alloc::Allocator;
 
 use super::{
-    GpuCmdByMut,
+    GpuCmd, GpuCmdByMut,
     chain::{Chain, Chainable, ChainableNext},
     mask,
 };
@@ -25,12 +25,12 @@ macro_rules! texenv_n {
     };
 }
 
-texenv_n!(E0, ctru_sys::GPUREG_TEXENV0_SOURCE);
-texenv_n!(E1, ctru_sys::GPUREG_TEXENV1_SOURCE);
-texenv_n!(E2, ctru_sys::GPUREG_TEXENV2_SOURCE);
-texenv_n!(E3, ctru_sys::GPUREG_TEXENV3_SOURCE);
-texenv_n!(E4, ctru_sys::GPUREG_TEXENV4_SOURCE);
-texenv_n!(E5, ctru_sys::GPUREG_TEXENV5_SOURCE);
+texenv_n!(E0, GPUREG_TEXENV0_SOURCE);
+texenv_n!(E1, GPUREG_TEXENV1_SOURCE);
+texenv_n!(E2, GPUREG_TEXENV2_SOURCE);
+texenv_n!(E3, GPUREG_TEXENV3_SOURCE);
+texenv_n!(E4, GPUREG_TEXENV4_SOURCE);
+texenv_n!(E5, GPUREG_TEXENV5_SOURCE);
 
 // E0 * Source = impl Chainable + ChainableNext<Next = TEC<E0,Operand>>
 
@@ -39,13 +39,15 @@ texenv_n!(E5, ctru_sys::GPUREG_TEXENV5_SOURCE);
 #[repr(u32)]
 pub enum Source {
     PrimaryColor,
+    ///Diffuse and ambient lighting, see `lighting`
     FragmentPrimaryColor,
+    ///Specular lighting, see `lighting`
     FragmentSecondaryColor,
     Texture0,
     Texture1,
     Texture2,
     Texture3,
-    ///Always returns Zero
+    ///The combiner buffer, see `UpdateBuffer`. Starts out as `BufferColor`.
     PreviousBuffer = 13,
     ///From `Color`
     Constant = 14,
@@ -240,6 +242,42 @@ impl<TE: TexEnv> Chainable for ScaleSplit<TE> {
     }
 }
 
+///Which of stages `0..=3` write their output into the combiner buffer, for later stages to read with `Source::PreviousBuffer`.
+///Only touches the texenv bits of `GPUREG_TEXENV_UPDATE_BUFFER`, the fog bits are left alone.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct UpdateBuffer {
+    pub rgb: [bool; 4],
+    pub alpha: [bool; 4],
+}
+
+impl GpuCmd for UpdateBuffer {
+    type Out = [u32; 2];
+    fn cmd(self) -> Self::Out {
+        let bits = |stages: [bool; 4]| {
+            stages
+                .iter()
+                .enumerate()
+                .fold(0, |acc, (i, &b)| acc | (b as u32) << i)
+        };
+        [
+            (bits(self.rgb) << 8) | (bits(self.alpha) << 12),
+            GPUREG_TEXENV_UPDATE_BUFFER | mask(0x2),
+        ]
+    }
+}
+
+///Initial contents of the combiner buffer
+///Note: `u32::from_le_bytes([r,g,b,a])`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BufferColor(pub u32);
+
+impl GpuCmd for BufferColor {
+    type Out = [u32; 2];
+    fn cmd(self) -> Self::Out {
+        [self.0, GPUREG_TEXENV_BUFFER_COLOR | mask(0xF)]
+    }
+}
+
 pub fn default_for<TE: TexEnv>() -> impl GpuCmdByMut {
     Chain
         * source_both::<TE>(Source::Previous, Source::Previous, Source::Previous)
@@ -268,4 +306,6 @@ pub fn all_defaults() -> impl GpuCmdByMut {
         + default_for::<E3>()
         + default_for::<E4>()
         + default_for::<E5>()
+        + UpdateBuffer::default()
+        + BufferColor(0xFFFFFFFF)
 }
@@ -0,0 +1,367 @@
+//! All six texenv stages as one value, checked before it is encoded.
+//!
+//! ```rust,ignore
+//! let pipeline = TexEnvPipeline::new()
+//!     .push(Stage::modulate(Source::Texture0, Source::PrimaryColor))?
+//!     .push(Stage::add_specular())?;
+//! Root + pipeline
+//! ```
+//!
+//! Passthrough stages are skipped when encoding, as are stages that were never set,
+//! so start from `texenv::all_defaults()` if earlier stages may still be configured.
+
+use super::{
+    GpuCmdByMut,
+    chain::Chain,
+    texenv::{
+        AlphaOp, Color, ColorOp, CombineMode, CombinerSplit, E0, E1, E2, E3, E4, E5, Operand,
+        Scale, ScaleSplit, Source, SourceSplit, TexEnv,
+    },
+};
+
+#[derive(Debug)]
+pub enum Error {
+    ///There are only 6 stages
+    TooManyStages,
+    ///The combine mode uses a different number of arguments than were given
+    WrongArgumentCount {
+        stage: usize,
+        alpha: bool,
+        expected: usize,
+        found: usize,
+    },
+    ///`Source::Previous` has no previous stage to read in stage 0
+    PreviousInFirstStage,
+    ///`Dot3RGB` only makes sense for the RGB combiner
+    Dot3InAlpha { stage: usize },
+}
+
+impl CombineMode {
+    ///How many of the three sources this mode reads
+    pub fn arguments(self) -> usize {
+        use CombineMode::*;
+        match self {
+            Replace => 1,
+            Modulate | Add | AddSigned | Subtract | Dot3RGB | Dot3RGBA => 2,
+            Interpolate | MultiplyThenAdd | AddThenMultiply => 3,
+        }
+    }
+}
+
+///One combiner, with exactly as many arguments as `mode` reads
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Function<Op> {
+    pub mode: CombineMode,
+    pub args: [Option<(Source, Op)>; 3],
+}
+
+impl<Op: Copy> Function<Op> {
+    pub fn replace(a: (Source, Op)) -> Self {
+        Function {
+            mode: CombineMode::Replace,
+            args: [Some(a), None, None],
+        }
+    }
+    pub fn binary(mode: CombineMode, a: (Source, Op), b: (Source, Op)) -> Self {
+        Function {
+            mode,
+            args: [Some(a), Some(b), None],
+        }
+    }
+    pub fn ternary(mode: CombineMode, a: (Source, Op), b: (Source, Op), c: (Source, Op)) -> Self {
+        Function {
+            mode,
+            args: [Some(a), Some(b), Some(c)],
+        }
+    }
+    fn sources(&self) -> impl Iterator<Item = Source> + '_ {
+        self.args.iter().flatten().map(|(source, _)| *source)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Stage {
+    pub rgb: Function<ColorOp>,
+    pub alpha: Function<AlphaOp>,
+    ///For `Source::Constant`.
+    ///Note: `u32::from_le_bytes([r,g,b,a])`
+    pub color: u32,
+    pub scale_rgb: Scale,
+    pub scale_alpha: Scale,
+}
+
+impl Stage {
+    ///Uses `rgb` and `alpha` with a constant color of 0 and no scaling
+    pub fn new(rgb: Function<ColorOp>, alpha: Function<AlphaOp>) -> Self {
+        Stage {
+            rgb,
+            alpha,
+            color: 0,
+            scale_rgb: Scale::X1,
+            scale_alpha: Scale::X1,
+        }
+    }
+    ///Passes the previous stage on unchanged, like `texenv::default_for`
+    pub fn passthrough() -> Self {
+        Self::replace(Source::Previous)
+    }
+    ///Outputs `source` as is, for example a texture
+    pub fn replace(source: Source) -> Self {
+        Self::new(
+            Function::replace((source, ColorOp::SourceColor)),
+            Function::replace((source, AlphaOp::SourceAlpha)),
+        )
+    }
+    ///`a * b`, for example a texture modulated with the vertex color
+    pub fn modulate(a: Source, b: Source) -> Self {
+        Self::new(
+            Function::binary(
+                CombineMode::Modulate,
+                (a, ColorOp::SourceColor),
+                (b, ColorOp::SourceColor),
+            ),
+            Function::binary(
+                CombineMode::Modulate,
+                (a, AlphaOp::SourceAlpha),
+                (b, AlphaOp::SourceAlpha),
+            ),
+        )
+    }
+    ///`texture` over the vertex color by the texture's alpha, keeping the vertex alpha
+    pub fn decal(texture: Source) -> Self {
+        Self::new(
+            Function::ternary(
+                CombineMode::Interpolate,
+                (texture, ColorOp::SourceColor),
+                (Source::PrimaryColor, ColorOp::SourceColor),
+                (texture, ColorOp::SourceAlpha),
+            ),
+            Function::replace((Source::PrimaryColor, AlphaOp::SourceAlpha)),
+        )
+    }
+    ///Adds specular lighting (`Source::FragmentSecondaryColor`) to the previous stage
+    pub fn add_specular() -> Self {
+        Self::new(
+            Function::binary(
+                CombineMode::Add,
+                (Source::Previous, ColorOp::SourceColor),
+                (Source::FragmentSecondaryColor, ColorOp::SourceColor),
+            ),
+            Function::replace((Source::Previous, AlphaOp::SourceAlpha)),
+        )
+    }
+    pub fn is_passthrough(&self) -> bool {
+        *self == Self::passthrough()
+    }
+    fn validate(&self, index: usize) -> Result<(), Error> {
+        let counts = [
+            (false, self.rgb.mode, self.rgb.args.iter().flatten().count()),
+            (
+                true,
+                self.alpha.mode,
+                self.alpha.args.iter().flatten().count(),
+            ),
+        ];
+        for (alpha, mode, found) in counts {
+            if mode.arguments() != found {
+                return Err(Error::WrongArgumentCount {
+                    stage: index,
+                    alpha,
+                    expected: mode.arguments(),
+                    found,
+                });
+            }
+        }
+        if self.alpha.mode == CombineMode::Dot3RGB {
+            return Err(Error::Dot3InAlpha { stage: index });
+        }
+        if index == 0
+            && !self.is_passthrough()
+            && self
+                .rgb
+                .sources()
+                .chain(self.alpha.sources())
+                .any(|s| s == Source::Previous)
+        {
+            return Err(Error::PreviousInFirstStage);
+        }
+        Ok(())
+    }
+    fn encode<TE: TexEnv>(self) -> impl GpuCmdByMut {
+        let rgb = self
+            .rgb
+            .args
+            .map(|a| a.unwrap_or((Source::Previous, ColorOp::SourceColor)));
+        let alpha = self
+            .alpha
+            .args
+            .map(|a| a.unwrap_or((Source::Previous, AlphaOp::SourceAlpha)));
+        Chain
+            * SourceSplit::<TE> {
+                rgb: (rgb[0].0, rgb[1].0, rgb[2].0),
+                alpha: (alpha[0].0, alpha[1].0, alpha[2].0),
+                te: Default::default(),
+            }
+            * Operand::<TE> {
+                rgb: (rgb[0].1, rgb[1].1, rgb[2].1),
+                alpha: (alpha[0].1, alpha[1].1, alpha[2].1),
+                te: Default::default(),
+            }
+            * CombinerSplit::<TE> {
+                rgb: self.rgb.mode,
+                alpha: self.alpha.mode,
+                te: Default::default(),
+            }
+            * Color::<TE>(self.color, Default::default())
+            * ScaleSplit::<TE> {
+                rgb: self.scale_rgb,
+                alpha: self.scale_alpha,
+                te: Default::default(),
+            }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct TexEnvPipeline {
+    stages: [Option<Stage>; 6],
+}
+
+impl TexEnvPipeline {
+    pub fn new() -> Self {
+        Default::default()
+    }
+    ///Sets the stage after the last one set
+    pub fn push(self, stage: Stage) -> Result<Self, Error> {
+        let index = self
+            .stages
+            .iter()
+            .rposition(Option::is_some)
+            .map_or(0, |i| i + 1);
+        self.set(index, stage)
+    }
+    pub fn set(mut self, index: usize, stage: Stage) -> Result<Self, Error> {
+        if index >= 6 {
+            return Err(Error::TooManyStages);
+        }
+        stage.validate(index)?;
+        self.stages[index] = Some(stage);
+        Ok(self)
+    }
+    pub fn stages(&self) -> &[Option<Stage>; 6] {
+        &self.stages
+    }
+    ///Skips validation, for stages read back from the registers
+    pub(crate) fn from_stages(stages: [Option<Stage>; 6]) -> Self {
+        TexEnvPipeline { stages }
+    }
+}
+
+impl GpuCmdByMut for TexEnvPipeline {
+    fn cmd_by_mut<A: std::alloc::Allocator>(self, buf: &mut Vec<u32, A>) {
+        for (index, stage) in self.stages.into_iter().enumerate() {
+            let Some(stage) = stage.filter(|s| !s.is_passthrough()) else {
+                continue;
+            };
+            match index {
+                0 => stage.encode::<E0>().cmd_by_mut(buf),
+                1 => stage.encode::<E1>().cmd_by_mut(buf),
+                2 => stage.encode::<E2>().cmd_by_mut(buf),
+                3 => stage.encode::<E3>().cmd_by_mut(buf),
+                4 => stage.encode::<E4>().cmd_by_mut(buf),
+                _ => stage.encode::<E5>().cmd_by_mut(buf),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gpucmd::{CONSECUTIVE_WRITING, extra_params, mask, regs::*};
+
+    #[test]
+    fn wrong_argument_count() {
+        let interpolate = Function::binary(
+            CombineMode::Interpolate,
+            (Source::Texture0, ColorOp::SourceColor),
+            (Source::PrimaryColor, ColorOp::SourceColor),
+        );
+        let stage = Stage::new(interpolate, Function::replace((Source::Texture0, AlphaOp::SourceAlpha)));
+        assert!(matches!(
+            TexEnvPipeline::new().push(stage),
+            Err(Error::WrongArgumentCount { stage: 0, alpha: false, expected: 3, found: 2 })
+        ));
+        let replace = Function {
+            mode: CombineMode::Replace,
+            args: [None; 3],
+        };
+        let stage = Stage::new(Function::replace((Source::Texture0, ColorOp::SourceColor)), replace);
+        assert!(matches!(
+            TexEnvPipeline::new().set(2, stage),
+            Err(Error::WrongArgumentCount { stage: 2, alpha: true, expected: 1, found: 0 })
+        ));
+    }
+
+    #[test]
+    fn previous_in_first_stage() {
+        assert!(matches!(
+            TexEnvPipeline::new().push(Stage::add_specular()),
+            Err(Error::PreviousInFirstStage)
+        ));
+        //Passing through is fine, and so is reading the previous stage after the first
+        TexEnvPipeline::new()
+            .push(Stage::passthrough())
+            .unwrap()
+            .push(Stage::add_specular())
+            .unwrap();
+    }
+
+    #[test]
+    fn dot3_in_alpha() {
+        let dot3 = Function::binary(
+            CombineMode::Dot3RGB,
+            (Source::Texture0, AlphaOp::SourceAlpha),
+            (Source::PrimaryColor, AlphaOp::SourceAlpha),
+        );
+        let stage = Stage::new(Function::replace((Source::Texture0, ColorOp::SourceColor)), dot3);
+        assert!(matches!(
+            TexEnvPipeline::new().set(1, stage),
+            Err(Error::Dot3InAlpha { stage: 1 })
+        ));
+    }
+
+    #[test]
+    fn too_many_stages() {
+        let full = (0..6).fold(TexEnvPipeline::new(), |pipeline, _| {
+            pipeline.push(Stage::replace(Source::Texture0)).unwrap()
+        });
+        assert!(matches!(full.push(Stage::replace(Source::Texture0)), Err(Error::TooManyStages)));
+        assert!(matches!(TexEnvPipeline::new().set(6, Stage::passthrough()), Err(Error::TooManyStages)));
+    }
+
+    #[test]
+    fn only_set_stages_are_written() {
+        //Stage 0 passes through and stage 1 is unset, so only stage 2 is written
+        let pipeline = TexEnvPipeline::new()
+            .push(Stage::passthrough())
+            .unwrap()
+            .set(2, Stage::modulate(Source::Texture0, Source::PrimaryColor))
+            .unwrap();
+        let mut buf = Vec::new();
+        pipeline.cmd_by_mut(&mut buf);
+        assert_eq!(
+            buf,
+            [
+                0x0F03_0F03,
+                GPUREG_TEXENV2_SOURCE | mask(0xF) | extra_params(4) | CONSECUTIVE_WRITING,
+                0,
+                CombineMode::Modulate as u32 | (CombineMode::Modulate as u32) << 16,
+                0,
+                0,
+            ]
+        );
+        let mut buf = Vec::new();
+        TexEnvPipeline::new().push(Stage::passthrough()).unwrap().cmd_by_mut(&mut buf);
+        assert!(buf.is_empty());
+    }
+}
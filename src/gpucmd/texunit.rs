@@ -0,0 +1,427 @@
+use super::regs::*;
+
+use super::{
+    CONSECUTIVE_WRITING, GpuCmd, GpuCmdByMut, Root,
+    chain::{Chain, Chainable, ChainableNext},
+    extra_params, mask,
+};
+#[cfg(target_os = "horizon")]
+use crate::texture::Texture;
+use crate::texture::{Format, Mode, TextureDescriptor};
+
+///Only units `0..=2` can sample from memory, unit 3 is the procedural texture unit.
+pub trait TexUnit: Sized + Default {
+    ///`GPUREG_TEXUNITi_BORDER_COLOR`, followed by `DIM`, `PARAM`, `LOD` and `ADDR`
+    const BASE: u32;
+    const TYPE: u32;
+    fn base(self) -> u32 {
+        Self::BASE
+    }
+}
+
+macro_rules! texunit_n {
+    ($name:ident,$base:expr,$ty:expr) => {
+        #[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+        pub struct $name;
+
+        impl TexUnit for $name {
+            const BASE: u32 = $base;
+            const TYPE: u32 = $ty;
+        }
+    };
+}
+
+texunit_n!(U0, GPUREG_TEXUNIT0_BORDER_COLOR, GPUREG_TEXUNIT0_TYPE);
+texunit_n!(U1, GPUREG_TEXUNIT1_BORDER_COLOR, GPUREG_TEXUNIT1_TYPE);
+texunit_n!(U2, GPUREG_TEXUNIT2_BORDER_COLOR, GPUREG_TEXUNIT2_TYPE);
+
+///Note: `u32::from_le_bytes([r,g,b,a])`
+///Successor = `Dim`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BorderColor<TU: TexUnit>(pub u32, pub TU);
+
+impl<TU: TexUnit> Chainable for BorderColor<TU> {
+    fn reg(&self) -> u32 {
+        TU::BASE + 0
+    }
+    fn param(self) -> u32 {
+        self.0
+    }
+}
+
+impl<TU: TexUnit> ChainableNext for BorderColor<TU> {
+    type Next = Dim<TU>;
+}
+
+///https://www.3dbrew.org/wiki/GPU/Internal_Registers#GPUREG_TEXUNITi_DIM
+///Successor = `Param`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Dim<TU: TexUnit> {
+    pub width: u16,
+    pub height: u16,
+    pub tu: TU,
+}
+
+impl<TU: TexUnit> Chainable for Dim<TU> {
+    fn reg(&self) -> u32 {
+        TU::BASE + 1
+    }
+    fn param(self) -> u32 {
+        (self.height as u32 & 0x7FF) | ((self.width as u32 & 0x7FF) << 16)
+    }
+}
+
+impl<TU: TexUnit> ChainableNext for Dim<TU> {
+    type Next = Param<TU>;
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u32)]
+pub enum Filter {
+    Nearest,
+    Linear,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u32)]
+pub enum Wrap {
+    ClampToEdge,
+    ///Samples outside of the texture return `BorderColor`
+    ClampToBorder,
+    Repeat,
+    MirroredRepeat,
+}
+
+///https://www.3dbrew.org/wiki/GPU/Internal_Registers#GPUREG_TEXUNITi_PARAM
+///Successor = `Lod`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Param<TU: TexUnit> {
+    pub mag_filter: Filter,
+    pub min_filter: Filter,
+    ///Filter used between mip levels
+    pub mip_filter: Filter,
+    ///Horizontal wrap mode
+    pub wrap_s: Wrap,
+    ///Vertical wrap mode
+    pub wrap_t: Wrap,
+    ///Must be set when sampling `Format::ETC1`
+    pub etc1: bool,
+    ///Must be set when sampling a shadow texture
+    pub shadow: bool,
+    ///Only `U0` supports anything other than `Mode::Ordinary` and `Mode::Disabled`
+    pub mode: Mode,
+    pub tu: TU,
+}
+
+impl<TU: TexUnit> Chainable for Param<TU> {
+    fn reg(&self) -> u32 {
+        TU::BASE + 2
+    }
+    fn param(self) -> u32 {
+        ((self.mag_filter as u32) << 1)
+            | ((self.min_filter as u32) << 2)
+            | if self.etc1 { 1 << 5 } else { 0 }
+            | ((self.wrap_t as u32) << 8)
+            | ((self.wrap_s as u32) << 12)
+            | if self.shadow { 1 << 20 } else { 0 }
+            | ((self.mip_filter as u32) << 24)
+            | ((self.mode as u32) << 28)
+    }
+}
+
+impl<TU: TexUnit> ChainableNext for Param<TU> {
+    type Next = Lod<TU>;
+}
+
+///https://www.3dbrew.org/wiki/GPU/Internal_Registers#GPUREG_TEXUNITi_LOD
+///Successor = `Addr`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Lod<TU: TexUnit> {
+    ///Added to the computed level of detail, in the range `-16.0..16.0`
+    pub bias: f32,
+    pub max_level: u8,
+    pub min_level: u8,
+    pub tu: TU,
+}
+
+impl<TU: TexUnit> Chainable for Lod<TU> {
+    fn reg(&self) -> u32 {
+        TU::BASE + 3
+    }
+    fn param(self) -> u32 {
+        //Fixed point 1.4.8
+        ((self.bias * 256.0) as i32 as u32 & 0x1FFF)
+            | ((self.max_level as u32 & 0xF) << 16)
+            | ((self.min_level as u32 & 0xF) << 24)
+    }
+}
+
+impl<TU: TexUnit> ChainableNext for Lod<TU> {
+    type Next = Addr<TU>;
+}
+
+///Physical address of the texture data, obtain this from `Texture::phys_addr`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Addr<TU: TexUnit>(pub u32, pub TU);
+
+impl<TU: TexUnit> Chainable for Addr<TU> {
+    fn reg(&self) -> u32 {
+        TU::BASE + 4
+    }
+    fn param(self) -> u32 {
+        self.0 >> 3
+    }
+}
+
+///Physical addresses of the `NegativeX..=NegativeZ` faces of a cube map on `U0`, `PositiveX` goes in `Addr`.
+///Only the low bits are used, the rest are shared with `Addr`, so every face must be in the same 32MiB region.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CubeAddr(pub [u32; 5]);
+
+impl GpuCmd for CubeAddr {
+    type Out = [u32; 6];
+    fn cmd(self) -> Self::Out {
+        let [a, b, c, d, e] = self.0.map(|x| (x >> 3) & 0x3FFFFF);
+        [
+            a,
+            GPUREG_TEXUNIT0_ADDR2 | mask(0xF) | extra_params(4) | CONSECUTIVE_WRITING,
+            b,
+            c,
+            d,
+            e,
+        ]
+    }
+}
+
+///https://www.3dbrew.org/wiki/GPU/Internal_Registers#GPUREG_TEXUNIT0_SHADOW
+///Only used when `U0` samples a `Mode::Shadow2d` or `Mode::ShadowCube` texture.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Shadow {
+    ///When Unset: the shadow map was rendered with an orthographic projection
+    pub perspective: bool,
+    ///Subtracted from the depth before comparing, `0.0..1.0`
+    pub bias: f32,
+}
+
+impl GpuCmd for Shadow {
+    type Out = [u32; 2];
+    fn cmd(self) -> Self::Out {
+        let bias = ((self.bias.abs() * (1 << 24) as f32) as u32).min((1 << 24) - 1);
+        [
+            (bias & !1) | if self.perspective { 0 } else { 1 },
+            GPUREG_TEXUNIT0_SHADOW | mask(0xF),
+        ]
+    }
+}
+
+///https://www.3dbrew.org/wiki/GPU/Internal_Registers#GPUREG_TEXUNITi_TYPE
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Type<TU: TexUnit>(pub Format, pub TU);
+
+impl<TU: TexUnit> Chainable for Type<TU> {
+    fn reg(&self) -> u32 {
+        TU::TYPE
+    }
+    fn param(self) -> u32 {
+        self.0 as u32
+    }
+}
+
+///https://www.3dbrew.org/wiki/GPU/Internal_Registers#GPUREG_TEXUNIT_CONFIG
+///Units that are not enabled here always sample as zero.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Config {
+    pub texture0: bool,
+    pub texture1: bool,
+    pub texture2: bool,
+    ///When Unset: Texture 2 uses texture coordinate 2
+    pub texture2_uses_texcoord1: bool,
+    ///Set this after writing new data to a texture that has already been sampled
+    pub clear_cache: bool,
+}
+
+impl GpuCmd for Config {
+    type Out = [u32; 2];
+    fn cmd(self) -> Self::Out {
+        [
+            if self.texture0 { 1 } else { 0 }
+                | if self.texture1 { 1 << 1 } else { 0 }
+                | if self.texture2 { 1 << 2 } else { 0 }
+                | if self.texture2_uses_texcoord1 { 1 << 13 } else { 0 }
+                | if self.clear_cache { 1 << 16 } else { 0 }
+                //Always set
+                | (1 << 12),
+            GPUREG_TEXUNIT_CONFIG | mask(0xF),
+        ]
+    }
+}
+
+///Everything about how a texture is sampled, apart from the texture itself
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Sampler {
+    pub mag_filter: Filter,
+    pub min_filter: Filter,
+    pub mip_filter: Filter,
+    pub wrap_s: Wrap,
+    pub wrap_t: Wrap,
+    ///Note: `u32::from_le_bytes([r,g,b,a])`
+    pub border_color: u32,
+    pub lod_bias: f32,
+    pub min_level: u8,
+    ///Clamped to the texture's `max_mip` when binding
+    pub max_level: u8,
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Sampler {
+            mag_filter: Filter::Nearest,
+            min_filter: Filter::Nearest,
+            mip_filter: Filter::Nearest,
+            wrap_s: Wrap::ClampToEdge,
+            wrap_t: Wrap::ClampToEdge,
+            border_color: 0,
+            lod_bias: 0.0,
+            min_level: 0,
+            max_level: 0xF,
+        }
+    }
+}
+
+///Points `TU` at `texture`, remember to also enable the unit with `Config`.
+///Cube maps, shadow textures and projection textures can only be bound to `U0`.
+#[cfg(target_os = "horizon")]
+pub fn bind<TU: TexUnit>(texture: &Texture, sampler: Sampler) -> Bind<TU> {
+    bind_at(*texture.descriptor(), texture.phys_addr(), sampler)
+}
+
+///Like `bind`, for texture data laid out like `descriptor` says, starting at physical address `addr`
+pub fn bind_at<TU: TexUnit>(descriptor: TextureDescriptor, addr: u32, sampler: Sampler) -> Bind<TU> {
+    let mode = descriptor.mode;
+    assert!(
+        TU::BASE == U0::BASE || mode == Mode::Ordinary,
+        "Only U0 supports {mode:?}"
+    );
+    Bind {
+        descriptor,
+        addr,
+        sampler,
+        tu: Default::default(),
+    }
+}
+
+pub struct Bind<TU: TexUnit> {
+    descriptor: TextureDescriptor,
+    addr: u32,
+    sampler: Sampler,
+    tu: TU,
+}
+
+impl<TU: TexUnit> GpuCmdByMut for Bind<TU> {
+    fn cmd_by_mut<A: std::alloc::Allocator>(self, buf: &mut Vec<u32, A>) {
+        let Bind { descriptor, addr, sampler, tu } = self;
+        let faces = descriptor.mode.face_list();
+        let face_addr = |face| addr + descriptor.face_offset(face) as u32;
+        let chain = Chain
+            * BorderColor(sampler.border_color, TU::default())
+            * Dim {
+                width: descriptor.width,
+                height: descriptor.height,
+                tu: Default::default(),
+            }
+            * Param {
+                mag_filter: sampler.mag_filter,
+                min_filter: sampler.min_filter,
+                mip_filter: sampler.mip_filter,
+                wrap_s: sampler.wrap_s,
+                wrap_t: sampler.wrap_t,
+                etc1: descriptor.format == Format::ETC1,
+                shadow: descriptor.mode.is_shadow(),
+                mode: descriptor.mode,
+                tu: Default::default(),
+            }
+            * Lod {
+                bias: sampler.lod_bias,
+                max_level: sampler.max_level.min(descriptor.max_mip),
+                min_level: sampler.min_level,
+                tu: Default::default(),
+            }
+            * Addr(face_addr(faces[0]), Default::default());
+        (Root + chain + Type(descriptor.format, tu)).cmd_by_mut(buf);
+        if descriptor.mode.is_cube() {
+            let mut addrs = [0; 5];
+            for (addr, &face) in addrs.iter_mut().zip(&faces[1..]) {
+                *addr = face_addr(face);
+            }
+            CubeAddr(addrs).cmd_by_mut(buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bind_words() {
+        let descriptor = TextureDescriptor {
+            width: 64,
+            height: 32,
+            max_mip: 2,
+            format: Format::RGB565,
+            mode: Mode::Ordinary,
+            vram: false,
+        };
+        let sampler = Sampler {
+            mag_filter: Filter::Linear,
+            wrap_s: Wrap::Repeat,
+            border_color: 0xFF0000FF,
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        bind_at::<U0>(descriptor, 0x1800_0000, sampler).cmd_by_mut(&mut buf);
+        assert_eq!(
+            buf,
+            [
+                0xFF0000FF,
+                GPUREG_TEXUNIT0_BORDER_COLOR | mask(0xF) | extra_params(4) | CONSECUTIVE_WRITING,
+                32 | (64 << 16),
+                (1 << 1) | (2 << 12),
+                //max_level is clamped to max_mip
+                2 << 16,
+                0x1800_0000 >> 3,
+                Format::RGB565 as u32,
+                GPUREG_TEXUNIT0_TYPE | mask(0xF),
+            ]
+        );
+    }
+
+    #[test]
+    fn bind_cube_map_words() {
+        let descriptor = TextureDescriptor {
+            width: 8,
+            height: 8,
+            max_mip: 0,
+            format: Format::RGBA8,
+            mode: Mode::CubeMap,
+            vram: false,
+        };
+        let mut buf = Vec::new();
+        bind_at::<U0>(descriptor, 0x1800_0000, Sampler::default()).cmd_by_mut(&mut buf);
+        let face = |face| (0x1800_0000 + descriptor.face_offset(face) as u32) >> 3;
+        let faces = Mode::CubeMap.face_list();
+        assert_eq!(buf.len(), 8 + 6);
+        assert_eq!(buf[5], face(faces[0]));
+        assert_eq!(
+            buf[8..],
+            [
+                face(faces[1]) & 0x3FFFFF,
+                GPUREG_TEXUNIT0_ADDR2 | mask(0xF) | extra_params(4) | CONSECUTIVE_WRITING,
+                face(faces[2]) & 0x3FFFFF,
+                face(faces[3]) & 0x3FFFFF,
+                face(faces[4]) & 0x3FFFFF,
+                face(faces[5]) & 0x3FFFFF,
+            ]
+        );
+    }
+}
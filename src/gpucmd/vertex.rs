@@ -0,0 +1,130 @@
+//! Vertex structs that describe their own attribute layout.
+//!
+//! ```rust,ignore
+//! #[derive(Clone, Copy, Pod, Zeroable)]
+//! #[repr(C)]
+//! struct MyVertex {
+//!     pos: [f32; 3],
+//!     color: [u8; 4],
+//! }
+//! impl_vertex!(MyVertex { pos, color });
+//!
+//! Root + MyVertex::layout() + MyVertex::buffers(&vertex_buffer, 0) + DrawArrays { .. }
+//! ```
+//!
+//! Field `i` is attribute `i` and goes into input register `v{i}`.
+
+use super::attribute::{Component, Layout, Type};
+#[cfg(target_os = "horizon")]
+use {super::attribute::Buffers, crate::buffer::Buffer};
+
+///Field types that can be loaded as an attribute
+pub trait AttributeType {
+    const TYPE: Type;
+    const COMPONENTS: u32;
+}
+
+macro_rules! attribute_type {
+    ($t:ty,$ty:expr) => {
+        impl AttributeType for $t {
+            const TYPE: Type = $ty;
+            const COMPONENTS: u32 = 1;
+        }
+        impl<const N: usize> AttributeType for [$t; N] {
+            const TYPE: Type = $ty;
+            const COMPONENTS: u32 = {
+                assert!(N >= 1 && N <= 4, "Attributes have 1 to 4 components");
+                N as u32
+            };
+        }
+    };
+}
+
+attribute_type!(i8, Type::Byte);
+attribute_type!(u8, Type::UnsignedByte);
+attribute_type!(i16, Type::Short);
+attribute_type!(f32, Type::Float);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct VertexAttribute {
+    pub ty: Type,
+    pub components: u32,
+    ///Byte offset inside the vertex
+    pub offset: usize,
+}
+
+impl VertexAttribute {
+    ///Used by `impl_vertex!`, `_field` only exists to infer `T`
+    pub const fn of<V, T: AttributeType>(_field: fn(&V) -> &T, offset: usize) -> Self {
+        VertexAttribute {
+            ty: T::TYPE,
+            components: T::COMPONENTS,
+            offset,
+        }
+    }
+    pub fn bytesize(&self) -> usize {
+        self.ty.bytesize() * self.components as usize
+    }
+}
+
+///Implement this with `impl_vertex!`
+pub trait Vertex: bytemuck::Pod {
+    ///In field order
+    const ATTRIBUTES: &'static [VertexAttribute];
+
+    ///Attribute `i` goes into `v{i}`, this also sets the number of attributes
+    fn layout() -> Layout {
+        Self::ATTRIBUTES
+            .iter()
+            .enumerate()
+            .fold(Layout::new(), |layout, (i, a)| layout.add(i as u32, a.ty, a.components))
+    }
+
+    ///The vertices are a `[Self]` starting `offset` bytes into `buffer`
+    #[cfg(target_os = "horizon")]
+    fn buffers(buffer: &Buffer, offset: usize) -> Buffers {
+        let stride = size_of::<Self>();
+        assert!(stride < 256, "Vertices must be smaller than 256 bytes");
+        Buffers::new().add(buffer, offset, stride as u8, &Self::components())
+    }
+
+    ///The attributes in memory order, with padding for the gaps between them
+    fn components() -> Vec<Component> {
+        let mut order: Vec<(usize, &VertexAttribute)> = Self::ATTRIBUTES.iter().enumerate().collect();
+        order.sort_by_key(|(_, a)| a.offset);
+        let mut components = Vec::with_capacity(order.len());
+        let mut end = 0;
+        for (i, a) in order {
+            pad(&mut components, a.offset - end);
+            components.push(Component::Attribute(i as u8));
+            end = a.offset + a.bytesize();
+        }
+        pad(&mut components, size_of::<Self>() - end);
+        components
+    }
+}
+
+///The loader aligns each attribute to its component size by itself, so only whole words of a gap need padding
+fn pad(components: &mut Vec<Component>, gap: usize) {
+    let mut gap = gap & !3;
+    while gap > 0 {
+        let bytes = gap.min(16);
+        components.push(Component::Padding(bytes as u8));
+        gap -= bytes;
+    }
+}
+
+///Implements `Vertex` for a `#[repr(C)]` struct, listing all of its fields in order
+#[macro_export]
+macro_rules! impl_vertex {
+    ($name:ident { $($field:ident),+ $(,)? }) => {
+        impl $crate::gpucmd::vertex::Vertex for $name {
+            const ATTRIBUTES: &'static [$crate::gpucmd::vertex::VertexAttribute] = &[
+                $($crate::gpucmd::vertex::VertexAttribute::of(
+                    |v: &$name| &v.$field,
+                    ::std::mem::offset_of!($name, $field),
+                )),+
+            ];
+        }
+    };
+}
@@ -0,0 +1,21 @@
+//! Everything that needs the 3DS itself is behind `cfg(target_os = "horizon")`.
+//! The rest, like encoding commands, converting textures and `reference::Renderer`, also builds on the host,
+//! so it can be tested there and used from build scripts.
+
+#![feature(allocator_api)]
+#![feature(generic_const_exprs)]
+#![feature(slice_split_once)]
+
+#[cfg(target_os = "horizon")]
+pub mod buffer;
+pub mod floater;
+pub mod gpucmd;
+pub mod queue;
+pub mod reference;
+#[cfg(target_os = "horizon")]
+pub mod renderbuffer;
+pub mod shader;
+pub mod shader_unfun;
+pub mod texture;
+#[cfg(target_os = "horizon")]
+pub mod vram;
@@ -1,19 +1,15 @@
-#![feature(allocator_api)]
-#![feature(generic_const_exprs)]
-#![feature(slice_split_once)]
+#[cfg(target_os = "horizon")]
+use ctru::prelude::*;
+#[cfg(target_os = "horizon")]
+use vultro_experiment::{gpucmd, queue, renderbuffer, shader};
 
-pub mod buffer;
-pub mod floater;
-pub mod gpucmd;
-pub mod queue;
-pub mod renderbuffer;
-pub mod shader;
-pub mod shader_unfun;
-pub mod texture;
-pub mod vram;
-
-use ctru::{prelude::*, services::gfx::Screen};
+///Everything but the hardware is in the library, which also builds on the host
+#[cfg(not(target_os = "horizon"))]
+fn main() {
+    eprintln!("This example only runs on the 3DS");
+}
 
+#[cfg(target_os = "horizon")]
 fn main() {
     let mut soc = Soc::new().expect("No Soc");
     let _ = soc.redirect_to_3dslink(true, true);
@@ -27,10 +23,10 @@ fn main() {
         )
     }
     .expect("No Gfx");
-    let mut bottom_screen = gfx.bottom_screen.borrow_mut();
-    let bottom_color_buffer =
+    let bottom_color_buffer = || {
         renderbuffer::ColorBuffer::new(240, 320, renderbuffer::ColorFormat::RGBA8)
-            .expect("No ColorBuffer");
+            .expect("No ColorBuffer")
+    };
     let inpos = shader::v(0).unwrap();
     let inclr = shader::v(1).unwrap();
     let outpos = shader::o(0).unwrap();
@@ -75,19 +71,20 @@ fn main() {
     let shader_entrypoint = shader_entrypoint.unwrap();
     let q = queue::Queue {};
     let some_command = {
-        use gpucmd::{CommandEncoder, Finish, alpha, cull_face, depth_map, misc, primitive};
+        use gpucmd::{CommandEncoder, Finish, alpha, color_operation, cull_face, depth_map, misc, primitive};
         CommandEncoder::new_with_capacity(512)
             + depth_map::EnabledScaleOffset(1.0, 0.0)
             + cull_face::BackCCW
             + alpha::Color(0)
-            + alpha::Blend::new(
+            + color_operation::FragmentOutput::Blend(alpha::Blend::new(
                 alpha::Add,
                 alpha::Add,
                 alpha::SrcAlpha,
                 alpha::OneMinusSrcAlpha,
                 alpha::SrcAlpha,
                 alpha::OneMinusSrcAlpha,
-            )
+            ))
+            - alpha::Test::disabled()
             + (some_shader, shader::VSH)
             + outmap
             + misc::VshEntrypoint(shader_entrypoint)
@@ -96,55 +93,53 @@ fn main() {
                 primitive_mode: primitive::Mode::Triangles,
             }
             + misc::NumAttr(2)
-            + &bottom_color_buffer
             + Finish
     };
     q.submit(&some_command)
         .expect("Could not submit command buffer to queue");
     ctru::services::gspgpu::wait_for_event(ctru::services::gspgpu::Event::P3D, false);
-    //TODO: Create Render Buffer
-    let some_other_command = {
-        use floater::f32x4tof24x4;
-        use gpucmd::{CommandEncoder, Finish, fixed_attrib, misc};
-        CommandEncoder::new_with_capacity(256)
-            + misc::NumVertices(3)
-            + misc::DrawingMode
-            + fixed_attrib::Index(0xF)
-            + fixed_attrib::Data(f32x4tof24x4([0.0, 0.0, -1.0, 1.0]))
-            + fixed_attrib::Data(f32x4tof24x4([1.0, 0.0, 0.0, 1.0]))
-            + fixed_attrib::Data(f32x4tof24x4([1.0, 0.0, -1.0, 1.0]))
-            + fixed_attrib::Data(f32x4tof24x4([0.0, 1.0, 0.0, 1.0]))
-            + fixed_attrib::Data(f32x4tof24x4([0.0, 1.0, -1.0, 1.0]))
-            + fixed_attrib::Data(f32x4tof24x4([0.0, 0.0, 1.0, 1.0]))
-            + misc::ConfigurationMode
-            + misc::ClearPostVertexCache
-            + misc::FlushFramebuffer
-            + Finish
-    };
+    let mut frames = queue::frame::FrameQueue::new(
+        &gfx,
+        vec![
+            queue::frame::Frame::new(None, Some(bottom_color_buffer())),
+            queue::frame::Frame::new(None, Some(bottom_color_buffer())),
+        ],
+        queue::TransferFlags {
+            flip_vert: false,
+            tiled_out: false,
+            output_width_less_than_input_width: false,
+            texture_copy: false,
+            tiled_to_tiled: false,
+            input_color_format: queue::TransferFormat::RGBA8,
+            output_color_format: queue::TransferFormat::RGB8,
+            block_tiling_mode: false,
+            scale_down_filter: queue::ScaleDownFilter::None,
+        },
+    )
+    .expect("There are frames to queue");
     while apt.main_loop() {
         hid.scan_input();
         if hid.keys_down().contains(KeyPad::START) {
             break;
         }
-        gfx.wait_for_vblank();
-        q.submit(&some_other_command).expect("Could not submit command buffer to queue");
-        ctru::services::gspgpu::wait_for_event(ctru::services::gspgpu::Event::P3D, false);
-        q.transfer_colorbuffer_to_framebuffer(
-            &bottom_color_buffer,
-            bottom_screen.raw_framebuffer(),
-            queue::TransferFlags {
-                flip_vert: false,
-                tiled_out: false,
-                output_width_less_than_input_width: false,
-                texture_copy: false,
-                tiled_to_tiled: false,
-                input_color_format: queue::TransferFormat::RGBA8,
-                output_color_format: queue::TransferFormat::RGB8,
-                block_tiling_mode: false,
-                scale_down_filter: queue::ScaleDownFilter::None,
-            },
-        ).expect("Could not perform display transfer");
-        ctru::services::gspgpu::wait_for_event(ctru::services::gspgpu::Event::PPF, false);
+        let frame = frames.begin().expect("Could not wait for a free frame");
+        frame.commands = {
+            use gpucmd::{CommandEncoder, Finish, immediate, misc, primitive};
+            CommandEncoder::new_with_capacity(256)
+                + frame.bottom.as_ref().unwrap()
+                + immediate::Draw {
+                    mode: primitive::Mode::Triangles,
+                    vertices: [
+                        [[0.0, 0.0, -1.0, 1.0], [1.0, 0.0, 0.0, 1.0]],
+                        [[1.0, 0.0, -1.0, 1.0], [0.0, 1.0, 0.0, 1.0]],
+                        [[0.0, 1.0, -1.0, 1.0], [0.0, 0.0, 1.0, 1.0]],
+                    ],
+                }
+                + misc::FlushFramebuffer
+                + Finish
+        };
+        frames.end().expect("Could not submit frame");
     }
+    frames.finish().expect("Could not finish frames");
     println!("Hello, world!");
 }
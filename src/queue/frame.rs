@@ -0,0 +1,172 @@
+//! Several frames in flight, so the CPU records one frame while the GPU renders and transfers the others.
+//!
+//! ```rust,ignore
+//! let mut frames = FrameQueue::new(&gfx, vec![Frame::new(None, Some(bottom_a)), Frame::new(None, Some(bottom_b))], flags).unwrap();
+//! while apt.main_loop() {
+//!     let frame = frames.begin()?;
+//!     let target = frame.bottom.as_ref().unwrap();
+//!     frame.commands = CommandEncoder::new() + target + scene + Finish;
+//!     frames.end()?;
+//! }
+//! frames.finish()?;
+//! ```
+//!
+//! What happens when is decided by `pacer::Pacer`.
+
+use std::cell::RefMut;
+
+use ctru::services::gfx::{BottomScreen, Gfx, Screen as _, Swap, TopScreen};
+
+use super::pacer::{Action, Event, EventSource, Pacer, Screen};
+use super::{Error, Queue, TransferFlags};
+use crate::{
+    gpucmd::{CmdBufAllocator, CommandBuffer},
+    renderbuffer::ColorBuffer,
+};
+
+///Waits with `gspgpu::wait_for_event`, on the next vblank rather than one that already passed
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Gsp;
+
+impl EventSource for Gsp {
+    fn wait(&mut self, event: Event) {
+        use ctru::services::gspgpu::{self, wait_for_event};
+        match event {
+            Event::P3D => wait_for_event(gspgpu::Event::P3D, false),
+            Event::PPF => wait_for_event(gspgpu::Event::PPF, false),
+            Event::VBlank => wait_for_event(gspgpu::Event::VBlank0, true),
+        }
+    }
+}
+
+///Everything the GPU reads while a frame is in flight
+pub struct Frame {
+    pub commands: CommandBuffer<CmdBufAllocator>,
+    ///Transferred to the top screen, if set
+    pub top: Option<ColorBuffer>,
+    ///Transferred to the bottom screen, if set
+    pub bottom: Option<ColorBuffer>,
+}
+
+impl Frame {
+    pub fn new(top: Option<ColorBuffer>, bottom: Option<ColorBuffer>) -> Self {
+        Frame {
+            commands: CommandBuffer {
+                buf: Vec::new_in(CmdBufAllocator),
+            },
+            top,
+            bottom,
+        }
+    }
+    fn screens(&self) -> Vec<Screen> {
+        self.top.iter().map(|_| Screen::Top).chain(self.bottom.iter().map(|_| Screen::Bottom)).collect()
+    }
+}
+
+///`Pacer` driving the GPU, with frames that own their command list and render targets
+pub struct FrameQueue<'g, E: EventSource = Gsp> {
+    pub queue: Queue,
+    pub flags: TransferFlags,
+    frames: Vec<Frame>,
+    pacer: Pacer,
+    events: E,
+    top: RefMut<'g, TopScreen>,
+    bottom: RefMut<'g, BottomScreen>,
+}
+
+impl<'g> FrameQueue<'g> {
+    ///`flags` are used for every display transfer, `None` without any frames
+    pub fn new(gfx: &'g Gfx, frames: Vec<Frame>, flags: TransferFlags) -> Option<Self> {
+        Self::with_events(gfx, frames, flags, Gsp)
+    }
+}
+
+impl<'g, E: EventSource> FrameQueue<'g, E> {
+    pub fn with_events(gfx: &'g Gfx, frames: Vec<Frame>, flags: TransferFlags, events: E) -> Option<Self> {
+        let pacer = Pacer::new(frames.len())?;
+        let mut top = gfx.top_screen.borrow_mut();
+        let mut bottom = gfx.bottom_screen.borrow_mut();
+        top.set_double_buffering(true);
+        bottom.set_double_buffering(true);
+        Some(FrameQueue {
+            queue: Queue {},
+            flags,
+            frames,
+            pacer,
+            events,
+            top,
+            bottom,
+        })
+    }
+    ///Waits for a free frame, whose command list and targets are then safe to change
+    pub fn begin(&mut self) -> Result<&mut Frame, Error> {
+        let FrameQueue {
+            queue,
+            flags,
+            frames,
+            pacer,
+            events,
+            top,
+            bottom,
+        } = self;
+        let index = pacer.begin(events, |action| run(queue, *flags, frames, top, bottom, action))?;
+        Ok(&mut frames[index])
+    }
+    ///Queues the frame from `begin`
+    pub fn end(&mut self) -> Result<(), Error> {
+        let FrameQueue {
+            queue,
+            flags,
+            frames,
+            pacer,
+            top,
+            bottom,
+            ..
+        } = self;
+        let index = pacer.recording().expect("FrameQueue::end called without FrameQueue::begin");
+        let screens = frames[index].screens();
+        pacer.end(&screens, |action| run(queue, *flags, frames, top, bottom, action))
+    }
+    ///Waits until every queued frame is on screen
+    pub fn finish(&mut self) -> Result<(), Error> {
+        let FrameQueue {
+            queue,
+            flags,
+            frames,
+            pacer,
+            events,
+            top,
+            bottom,
+        } = self;
+        pacer.finish(events, |action| run(queue, *flags, frames, top, bottom, action))
+    }
+}
+
+fn run(
+    queue: &Queue,
+    flags: TransferFlags,
+    frames: &[Frame],
+    top: &mut TopScreen,
+    bottom: &mut BottomScreen,
+    action: Action,
+) -> Result<(), Error> {
+    match action {
+        Action::Submit(i) => queue.submit(&frames[i].commands),
+        Action::Transfer(i, screen) => {
+            let (target, framebuffer) = match screen {
+                Screen::Top => (&frames[i].top, top.raw_framebuffer()),
+                Screen::Bottom => (&frames[i].bottom, bottom.raw_framebuffer()),
+            };
+            queue.transfer_colorbuffer_to_framebuffer(target.as_ref().unwrap(), framebuffer, flags)
+        }
+        Action::Present(i) => {
+            if frames[i].top.is_some() {
+                top.swap_buffers();
+            }
+            if frames[i].bottom.is_some() {
+                bottom.swap_buffers();
+            }
+            Ok(())
+        }
+    }
+}
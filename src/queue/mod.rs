@@ -1,8 +1,15 @@
+#[cfg(target_os = "horizon")]
+pub mod frame;
+pub mod pacer;
+
 use std::ffi::c_void;
 
+#[cfg(target_os = "horizon")]
 use ctru::services::gfx::RawFrameBuffer;
+#[cfg(target_os = "horizon")]
 use ctru_sys::gspSubmitGxCommand;
 
+#[cfg(target_os = "horizon")]
 use crate::{buffer::BufferSlice, renderbuffer::{dim, ColorBuffer}};
 
 pub type GxCommand = [u32; 8];
@@ -22,6 +29,7 @@ pub enum Error {
 ///Process Command List
 ///Memory Fill ALIGNED
 
+#[cfg(target_os = "horizon")]
 impl Queue {
     pub unsafe fn submit_command(&self, command: impl Into<GxCommand>) -> Result<(), Error> {
         let command: GxCommand = command.into();
@@ -206,7 +214,7 @@ pub(crate) fn gx_display_transfer(
 
 //So, it appears there's a thing called the "Transfer Engine"
 //It can do texture swizzles, hardware accelerated.
-//I'll most likely want to expose it in some form in the future, but for now i can use the swizzle_3ds crate.
+//I'll most likely want to expose it in some form in the future, but for now `texture::tile` does it on the CPU.
 
 pub(crate) fn gx_texture_copy(
     src: *const c_void,
@@ -0,0 +1,383 @@
+//! Decides when frames in flight are submitted, transferred and presented.
+//!
+//! `Pacer` neither touches the GPU nor waits on its own, so it runs on the host against `Simulated` events.
+//! Only one command list and one display transfer are ever outstanding,
+//! because GSP events of the same kind that arrive before they're waited on collapse into one.
+//! https://www.3dbrew.org/wiki/GSP_Shared_Memory#Interrupt_Queue
+
+use std::collections::VecDeque;
+
+///What frames wait on
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Event {
+    ///A command list finished
+    P3D,
+    ///A display transfer finished
+    PPF,
+    ///The top screen started a new frame
+    VBlank,
+}
+
+///Blocks until an event fires, `frame::Gsp` on hardware
+pub trait EventSource {
+    ///Returns right away if a `P3D` or `PPF` already fired since it was last waited on,
+    ///but always waits for the next `VBlank`
+    fn wait(&mut self, event: Event);
+}
+
+///Every event fires as soon as it's waited on, and is logged
+#[derive(Clone, Default, Debug)]
+pub struct Simulated {
+    pub waited: Vec<Event>,
+}
+
+impl EventSource for Simulated {
+    fn wait(&mut self, event: Event) {
+        self.waited.push(event);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Screen {
+    Top,
+    Bottom,
+}
+
+///The work `Pacer` hands out, by frame index
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    ///Submit the frame's command list
+    Submit(usize),
+    ///Transfer the frame's render target for a screen to that screen
+    Transfer(usize, Screen),
+    ///Swap the buffers of the frame's screens, shown from the next vblank on
+    Present(usize),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum State {
+    Free,
+    Recording,
+    Queued,
+    Rendering,
+    ///The transfer of the `n`th screen is next
+    Rendered(usize),
+    ///Waiting on the transfer of the `n`th screen
+    Transferring(usize),
+    Transferred,
+    ///Waiting on the vblank that shows the frame
+    Presenting,
+}
+
+#[derive(Clone, Debug)]
+struct Slot {
+    state: State,
+    screens: Vec<Screen>,
+}
+
+///Schedules frames through recording, rendering, transfer and presentation, in order
+#[derive(Clone, Debug)]
+pub struct Pacer {
+    slots: Vec<Slot>,
+    ///Frames past `Recording`, oldest first
+    in_flight: VecDeque<usize>,
+    recording: Option<usize>,
+}
+
+impl Pacer {
+    ///`frames` is usually 2 for double or 3 for triple buffering, `None` if it's 0
+    pub fn new(frames: usize) -> Option<Self> {
+        if frames == 0 {
+            return None;
+        }
+        Some(Pacer {
+            slots: vec![
+                Slot {
+                    state: State::Free,
+                    screens: Vec::new()
+                };
+                frames
+            ],
+            in_flight: VecDeque::new(),
+            recording: None,
+        })
+    }
+    ///The frame being recorded, between `begin` and `end`
+    pub fn recording(&self) -> Option<usize> {
+        self.recording
+    }
+    ///Waits until a frame is free, then starts recording it.
+    ///Panics if the previous frame wasn't ended.
+    pub fn begin<E>(
+        &mut self,
+        events: &mut impl EventSource,
+        mut act: impl FnMut(Action) -> Result<(), E>,
+    ) -> Result<usize, E> {
+        assert!(self.recording.is_none(), "Pacer::begin called twice without Pacer::end");
+        loop {
+            if let Some(index) = self.slots.iter().position(|s| s.state == State::Free) {
+                self.slots[index].state = State::Recording;
+                self.recording = Some(index);
+                return Ok(index);
+            }
+            self.wait(events, &mut act)?;
+        }
+    }
+    ///Queues the recorded frame, to be transferred to `screens` in order, and starts whatever can start
+    pub fn end<E>(
+        &mut self,
+        screens: &[Screen],
+        mut act: impl FnMut(Action) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let index = self.recording.take().expect("Pacer::end called without Pacer::begin");
+        self.slots[index] = Slot {
+            state: State::Queued,
+            screens: screens.to_vec(),
+        };
+        self.in_flight.push_back(index);
+        self.kick(&mut act)
+    }
+    ///Waits until every queued frame was presented
+    pub fn finish<E>(
+        &mut self,
+        events: &mut impl EventSource,
+        mut act: impl FnMut(Action) -> Result<(), E>,
+    ) -> Result<(), E> {
+        while !self.in_flight.is_empty() {
+            self.wait(events, &mut act)?;
+        }
+        Ok(())
+    }
+    fn oldest(&self, state: impl Fn(State) -> bool) -> Option<usize> {
+        self.in_flight.iter().copied().find(|&i| state(self.slots[i].state))
+    }
+    ///The screens the frame's image is already in
+    fn transferred(&self, i: usize) -> &[Screen] {
+        let slot = &self.slots[i];
+        match slot.state {
+            State::Rendered(n) => &slot.screens[..n],
+            State::Transferring(n) => &slot.screens[..=n],
+            State::Transferred | State::Presenting => &slot.screens,
+            _ => &[],
+        }
+    }
+    ///A frame's image stays in the buffer the next transfer to that screen would overwrite until the vblank that shows it
+    fn screen_busy(&self, screen: Screen) -> bool {
+        self.in_flight.iter().any(|&i| self.transferred(i).contains(&screen))
+    }
+    ///Starts the next step of every frame that doesn't wait on the GPU or the screen
+    fn kick<E>(&mut self, act: &mut impl FnMut(Action) -> Result<(), E>) -> Result<(), E> {
+        loop {
+            let busy = |f: fn(State) -> bool| self.oldest(f).is_some();
+            if !busy(|s| s == State::Rendering)
+                && let Some(i) = self.oldest(|s| s == State::Queued)
+            {
+                act(Action::Submit(i))?;
+                self.slots[i].state = State::Rendering;
+                continue;
+            }
+            if !busy(|s| matches!(s, State::Transferring(_)))
+                && let Some(i) = self.oldest(|s| matches!(s, State::Rendered(_)))
+            {
+                let State::Rendered(n) = self.slots[i].state else { unreachable!() };
+                match self.slots[i].screens.get(n).copied() {
+                    None => {
+                        self.slots[i].state = State::Transferred;
+                        continue;
+                    }
+                    Some(screen) if !self.screen_busy(screen) => {
+                        act(Action::Transfer(i, screen))?;
+                        self.slots[i].state = State::Transferring(n);
+                        continue;
+                    }
+                    //Held until the vblank of the frame before
+                    Some(_) => {}
+                }
+            }
+            //Frames are shown in the order they were queued
+            if !busy(|s| s == State::Presenting)
+                && let Some(i) = self.in_flight.front().copied().filter(|&i| self.slots[i].state == State::Transferred)
+            {
+                act(Action::Present(i))?;
+                self.slots[i].state = State::Presenting;
+                continue;
+            }
+            return Ok(());
+        }
+    }
+    ///Waits on the oldest frame that waits on the GPU, or else on the screen, then moves it along.
+    ///The GPU comes first so the next command list goes out without waiting for a vblank.
+    fn wait<E>(
+        &mut self,
+        events: &mut impl EventSource,
+        act: &mut impl FnMut(Action) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let waiting = self
+            .oldest(|s| matches!(s, State::Rendering | State::Transferring(_)))
+            .or_else(|| self.oldest(|s| s == State::Presenting));
+        let Some(i) = waiting else {
+            panic!("Pacer has no free frame and nothing in flight");
+        };
+        let slot = &mut self.slots[i];
+        match slot.state {
+            State::Rendering => {
+                events.wait(Event::P3D);
+                slot.state = State::Rendered(0);
+            }
+            State::Transferring(n) => {
+                events.wait(Event::PPF);
+                slot.state = State::Rendered(n + 1);
+            }
+            _ => {
+                events.wait(Event::VBlank);
+                slot.state = State::Free;
+                self.in_flight.retain(|&f| f != i);
+            }
+        }
+        self.kick(act)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    use Action::*;
+    use Event::*;
+    use Screen::*;
+    use Step::*;
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    enum Step {
+        Act(Action),
+        Wait(Event),
+    }
+
+    ///`Simulated`, with its events logged alongside the actions
+    struct Logged<'a>(Simulated, &'a RefCell<Vec<Step>>);
+
+    impl EventSource for Logged<'_> {
+        fn wait(&mut self, event: Event) {
+            self.0.wait(event);
+            self.1.borrow_mut().push(Wait(event));
+        }
+    }
+
+    ///Records `count` frames transferred to `screens` on a pacer with `frames` frames, then finishes
+    fn run(frames: usize, count: usize, screens: &[Screen]) -> (Vec<usize>, Vec<Step>) {
+        let log = RefCell::new(Vec::new());
+        let act = |action| {
+            log.borrow_mut().push(Act(action));
+            Ok::<_, ()>(())
+        };
+        let mut pacer = Pacer::new(frames).unwrap();
+        let mut events = Logged(Simulated::default(), &log);
+        let begun = (0..count)
+            .map(|_| {
+                let index = pacer.begin(&mut events, act).unwrap();
+                pacer.end(screens, act).unwrap();
+                index
+            })
+            .collect();
+        pacer.finish(&mut events, act).unwrap();
+        let log = log.take();
+        let waited: Vec<_> = log.iter().filter_map(|s| match s {
+            Wait(e) => Some(*e),
+            _ => None,
+        }).collect();
+        assert_eq!(events.0.waited, waited);
+        (begun, log)
+    }
+
+    #[test]
+    fn zero_frames() {
+        assert!(Pacer::new(0).is_none());
+    }
+
+    #[test]
+    fn double_buffering() {
+        let (begun, log) = run(2, 3, &[Bottom]);
+        assert_eq!(begun, [0, 1, 0]);
+        assert_eq!(log, [
+            Act(Submit(0)),
+            Wait(P3D),
+            Act(Submit(1)),
+            Act(Transfer(0, Bottom)),
+            Wait(PPF),
+            Act(Present(0)),
+            Wait(P3D),
+            //Frame 0 is still on screen until this vblank
+            Wait(VBlank),
+            Act(Transfer(1, Bottom)),
+            Act(Submit(0)),
+            Wait(PPF),
+            Act(Present(1)),
+            Wait(P3D),
+            Wait(VBlank),
+            Act(Transfer(0, Bottom)),
+            Wait(PPF),
+            Act(Present(0)),
+            Wait(VBlank),
+        ]);
+    }
+
+    #[test]
+    fn triple_buffering() {
+        let (begun, log) = run(3, 4, &[Bottom]);
+        assert_eq!(begun, [0, 1, 2, 0]);
+        assert_eq!(log, [
+            Act(Submit(0)),
+            Wait(P3D),
+            Act(Submit(1)),
+            Act(Transfer(0, Bottom)),
+            Wait(PPF),
+            Act(Present(0)),
+            Wait(P3D),
+            Act(Submit(2)),
+            //Frames 1 and 2 are rendered, but both wait for frame 0's vblank
+            Wait(P3D),
+            Wait(VBlank),
+            Act(Transfer(1, Bottom)),
+            Act(Submit(0)),
+            Wait(PPF),
+            Act(Present(1)),
+            Wait(P3D),
+            //Frame 2 can't overwrite frame 1 before it's shown
+            Wait(VBlank),
+            Act(Transfer(2, Bottom)),
+            Wait(PPF),
+            Act(Present(2)),
+            Wait(VBlank),
+            Act(Transfer(0, Bottom)),
+            Wait(PPF),
+            Act(Present(0)),
+            Wait(VBlank),
+        ]);
+    }
+
+    #[test]
+    fn both_screens() {
+        let (begun, log) = run(2, 2, &[Top, Bottom]);
+        assert_eq!(begun, [0, 1]);
+        assert_eq!(log, [
+            Act(Submit(0)),
+            Wait(P3D),
+            Act(Submit(1)),
+            Act(Transfer(0, Top)),
+            Wait(PPF),
+            Act(Transfer(0, Bottom)),
+            Wait(PPF),
+            Act(Present(0)),
+            Wait(P3D),
+            Wait(VBlank),
+            Act(Transfer(1, Top)),
+            Wait(PPF),
+            Act(Transfer(1, Bottom)),
+            Wait(PPF),
+            Act(Present(1)),
+            Wait(VBlank),
+        ]);
+    }
+}
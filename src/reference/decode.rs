@@ -0,0 +1,102 @@
+//! Splits a command buffer back into single register writes.
+//!
+//! ```rust,ignore
+//! for write in decode::writes(&buf.buf) {
+//!     let write = write?;
+//!     println!("{:#05X} = {:#010X} (mask {:#X})", write.reg, write.value, write.mask);
+//! }
+//! ```
+//! https://www.3dbrew.org/wiki/GPU/Internal_Registers#Command_Buffer
+
+use crate::gpucmd::CONSECUTIVE_WRITING;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Error {
+    ///The header at word `at` announces more parameters than there are words left
+    Truncated { at: usize },
+}
+
+///One write of `value` to `reg`, only the bytes set in `mask` are written
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Write {
+    pub reg: u32,
+    pub value: u32,
+    pub mask: u32,
+}
+
+impl Write {
+    ///`old` with the masked bytes replaced
+    pub fn apply(self, old: u32) -> u32 {
+        let bytes = (0..4).fold(0, |acc, i| {
+            if (self.mask >> i) & 1 == 1 { acc | 0xFF << (i * 8) } else { acc }
+        });
+        (old & !bytes) | (self.value & bytes)
+    }
+}
+
+///Every write in `words`, in order. Stops after the first error.
+pub fn writes(words: &[u32]) -> Writes<'_> {
+    Writes {
+        words,
+        at: 0,
+        pending: None,
+    }
+}
+
+struct Pending {
+    reg: u32,
+    mask: u32,
+    consecutive: bool,
+    remaining: usize,
+}
+
+pub struct Writes<'a> {
+    words: &'a [u32],
+    at: usize,
+    pending: Option<Pending>,
+}
+
+impl<'a> Iterator for Writes<'a> {
+    type Item = Result<Write, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(pending) = &mut self.pending {
+            if pending.remaining > 0 {
+                pending.remaining -= 1;
+                if pending.consecutive {
+                    pending.reg += 1;
+                }
+                let write = Write {
+                    reg: pending.reg,
+                    value: self.words[self.at],
+                    mask: pending.mask,
+                };
+                self.at += 1;
+                return Some(Ok(write));
+            }
+            self.pending = None;
+            //Every command is padded to 8 bytes
+            self.at += self.at & 1;
+        }
+        let [value, header] = *self.words.get(self.at..)?.first_chunk::<2>()?;
+        let extra = ((header >> 20) & 0xFF) as usize;
+        if self.words.len() < self.at + 2 + extra {
+            let at = self.at;
+            self.at = self.words.len();
+            return Some(Err(Error::Truncated { at }));
+        }
+        let write = Write {
+            reg: header & 0xFFFF,
+            value,
+            mask: (header >> 16) & 0xF,
+        };
+        self.at += 2;
+        self.pending = Some(Pending {
+            reg: write.reg,
+            mask: write.mask,
+            consecutive: header & CONSECUTIVE_WRITING != 0,
+            remaining: extra,
+        });
+        Some(Ok(write))
+    }
+}
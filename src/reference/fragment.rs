@@ -0,0 +1,225 @@
+//! Alpha test, blending and logic ops, run on the texenv output.
+
+use super::Rgba;
+use crate::gpucmd::{
+    alpha::{Blend, Equation, Factor, Function, Test},
+    logic_op::LogicOp,
+};
+
+///`true` if the fragment passes, and is kept
+pub fn alpha_test(test: &Test, alpha: u8) -> bool {
+    if !test.enabled {
+        return true;
+    }
+    compare(test.function, alpha as u16, test.reference_value)
+}
+
+///`value <function> reference`, also used for the depth and stencil tests
+pub fn compare<T: PartialOrd>(function: Function, value: T, reference: T) -> bool {
+    match function {
+        Function::Never => false,
+        Function::Always => true,
+        Function::Equal => value == reference,
+        Function::NotEqual => value != reference,
+        Function::LessThan => value < reference,
+        Function::LessThanOrEqual => value <= reference,
+        Function::GreaterThan => value > reference,
+        Function::GreaterThanOrEqual => value >= reference,
+    }
+}
+
+///The four channels a factor multiplies with, in `0.0..=1.0`
+fn factor(factor: Factor, src: [f32; 4], dst: [f32; 4], constant: [f32; 4]) -> [f32; 4] {
+    let one_minus = |x: [f32; 4]| x.map(|c| 1.0 - c);
+    match factor {
+        Factor::Zero => [0.0; 4],
+        Factor::One => [1.0; 4],
+        Factor::SrcColor => src,
+        Factor::OneMinusSrcColor => one_minus(src),
+        Factor::DstColor => dst,
+        Factor::OneMinusDstColor => one_minus(dst),
+        Factor::SrcAlpha => [src[3]; 4],
+        Factor::OneMinusSrcAlpha => [1.0 - src[3]; 4],
+        Factor::DstAlpha => [dst[3]; 4],
+        Factor::OneMinusDstAlpha => [1.0 - dst[3]; 4],
+        Factor::ConstantColor => constant,
+        Factor::OneMinusConstantColor => one_minus(constant),
+        Factor::ConstantAlpha => [constant[3]; 4],
+        Factor::OneMinusConstantAlpha => [1.0 - constant[3]; 4],
+        Factor::SaturatedAlpha => {
+            let f = src[3].min(1.0 - dst[3]);
+            [f, f, f, 1.0]
+        }
+    }
+}
+
+fn equation(equation: Equation, src: f32, dst: f32, src_factor: f32, dst_factor: f32) -> f32 {
+    match equation {
+        Equation::Add => src * src_factor + dst * dst_factor,
+        Equation::Subtract => src * src_factor - dst * dst_factor,
+        Equation::ReverseSubtract => dst * dst_factor - src * src_factor,
+        //Like OpenGL, min and max ignore the factors
+        Equation::Min => src.min(dst),
+        Equation::Max => src.max(dst),
+    }
+}
+
+///`src` is the texenv output, `dst` what is in the color buffer, `constant` is `alpha::Color`
+pub fn blend(blend: &Blend, src: Rgba, dst: Rgba, constant: Rgba) -> Rgba {
+    let norm = |x: Rgba| x.map(|c| c as f32 / 255.0);
+    let (s, d, k) = (norm(src), norm(dst), norm(constant));
+    let color_src = factor(blend.color_src, s, d, k);
+    let color_dst = factor(blend.color_dst, s, d, k);
+    let alpha_src = factor(blend.alpha_src, s, d, k);
+    let alpha_dst = factor(blend.alpha_dst, s, d, k);
+    std::array::from_fn(|c| {
+        let value = if c < 3 {
+            equation(blend.color_eq, s[c], d[c], color_src[c], color_dst[c])
+        } else {
+            equation(blend.alpha_eq, s[c], d[c], alpha_src[c], alpha_dst[c])
+        };
+        (value.clamp(0.0, 1.0) * 255.0).round() as u8
+    })
+}
+
+///Bitwise on every channel
+pub fn logic_op(op: LogicOp, src: Rgba, dst: Rgba) -> Rgba {
+    std::array::from_fn(|c| {
+        let (s, d) = (src[c], dst[c]);
+        match op {
+            LogicOp::Clear => 0,
+            LogicOp::And => s & d,
+            LogicOp::ReverseAnd => s & !d,
+            LogicOp::Copy => s,
+            LogicOp::Set => 0xFF,
+            LogicOp::InvertedCopy => !s,
+            LogicOp::Noop => d,
+            LogicOp::Invert => !d,
+            LogicOp::Nand => !(s & d),
+            LogicOp::Or => s | d,
+            LogicOp::Nor => !(s | d),
+            LogicOp::Xor => s ^ d,
+            LogicOp::Equivalent => !(s ^ d),
+            LogicOp::InvertedAnd => !s & d,
+            LogicOp::ReverseOr => s | !d,
+            LogicOp::InvertedOr => !s | d,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alpha_test_functions() {
+        assert!(alpha_test(&Test::disabled(), 0));
+        //Passes for alpha 127, 128 and 129 against a reference of 128
+        for (i, (function, expected)) in [
+            (Function::Never, [false, false, false]),
+            (Function::Always, [true, true, true]),
+            (Function::Equal, [false, true, false]),
+            (Function::NotEqual, [true, false, true]),
+            (Function::LessThan, [true, false, false]),
+            (Function::LessThanOrEqual, [true, true, false]),
+            (Function::GreaterThan, [false, false, true]),
+            (Function::GreaterThanOrEqual, [false, true, true]),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let test = Test::new(function, 128);
+            assert_eq!([127, 128, 129].map(|a| alpha_test(&test, a)), expected, "function {i}");
+        }
+    }
+
+    #[test]
+    fn blend_equations() {
+        let (src, dst) = ([200, 100, 50, 255], [100, 150, 50, 0]);
+        for (i, (eq, expected)) in [
+            (Equation::Add, [255, 250, 100, 255]),
+            (Equation::Subtract, [100, 0, 0, 255]),
+            (Equation::ReverseSubtract, [0, 50, 0, 0]),
+            (Equation::Min, [100, 100, 50, 0]),
+            (Equation::Max, [200, 150, 50, 255]),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let b = Blend::new(eq, eq, Factor::One, Factor::One, Factor::One, Factor::One);
+            assert_eq!(blend(&b, src, dst, [0; 4]), expected, "equation {i}");
+        }
+    }
+
+    #[test]
+    fn blend_factors() {
+        let (src, dst, constant) = ([200, 100, 50, 192], [100, 150, 250, 64], [10, 20, 30, 40]);
+        for (i, (f, expected)) in [
+            (Factor::Zero, [0, 0, 0]),
+            (Factor::One, [200, 100, 50]),
+            (Factor::SrcColor, [157, 39, 10]),
+            (Factor::OneMinusSrcColor, [43, 61, 40]),
+            (Factor::DstColor, [78, 59, 49]),
+            (Factor::OneMinusDstColor, [122, 41, 1]),
+            (Factor::SrcAlpha, [151, 75, 38]),
+            (Factor::OneMinusSrcAlpha, [49, 25, 12]),
+            (Factor::DstAlpha, [50, 25, 13]),
+            (Factor::OneMinusDstAlpha, [150, 75, 37]),
+            (Factor::ConstantColor, [8, 8, 6]),
+            (Factor::OneMinusConstantColor, [192, 92, 44]),
+            (Factor::ConstantAlpha, [31, 16, 8]),
+            (Factor::OneMinusConstantAlpha, [169, 84, 42]),
+            //min(192, 255 - 64)
+            (Factor::SaturatedAlpha, [150, 75, 37]),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let b = Blend::new(Equation::Add, Equation::Add, f, Factor::Zero, Factor::One, Factor::Zero);
+            let out = blend(&b, src, dst, constant);
+            assert_eq!(out[..3], expected, "factor {i}");
+            assert_eq!(out[3], 192, "factor {i}");
+        }
+    }
+
+    #[test]
+    fn usual_alpha_blending() {
+        let b = Blend::new(
+            Equation::Add,
+            Equation::Add,
+            Factor::SrcAlpha,
+            Factor::OneMinusSrcAlpha,
+            Factor::SrcAlpha,
+            Factor::OneMinusSrcAlpha,
+        );
+        assert_eq!(blend(&b, [255, 0, 0, 128], [0, 0, 255, 255], [0; 4]), [128, 0, 127, 191]);
+    }
+
+    #[test]
+    fn logic_ops() {
+        let (s, d) = (0xCA, 0xAC);
+        for (i, (op, expected)) in [
+            (LogicOp::Clear, 0x00),
+            (LogicOp::And, 0x88),
+            (LogicOp::ReverseAnd, 0x42),
+            (LogicOp::Copy, 0xCA),
+            (LogicOp::Set, 0xFF),
+            (LogicOp::InvertedCopy, 0x35),
+            (LogicOp::Noop, 0xAC),
+            (LogicOp::Invert, 0x53),
+            (LogicOp::Nand, 0x77),
+            (LogicOp::Or, 0xEE),
+            (LogicOp::Nor, 0x11),
+            (LogicOp::Xor, 0x66),
+            (LogicOp::Equivalent, 0x99),
+            (LogicOp::InvertedAnd, 0x24),
+            (LogicOp::ReverseOr, 0xDB),
+            (LogicOp::InvertedOr, 0xBD),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            assert_eq!(logic_op(op, [s; 4], [d; 4]), [expected; 4], "logic op {i}");
+        }
+    }
+}
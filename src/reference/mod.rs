@@ -0,0 +1,86 @@
+//! A CPU model of the fragment pipeline, for checking material setups without hardware.
+//!
+//! It is driven by the same types that encode the commands, so a material can be built once,
+//! then both sent to the GPU and run through `shade` in a test.
+//!
+//! ```rust
+//! # use vultro_experiment::gpucmd::{texenv::Source, texenv_pipeline::{Stage, TexEnvPipeline}};
+//! # use vultro_experiment::reference::{Inputs, State, shade};
+//! let pipeline = TexEnvPipeline::new().push(Stage::modulate(Source::Texture0, Source::PrimaryColor)).unwrap();
+//! let state = State { pipeline, ..Default::default() };
+//! let inputs = Inputs { primary_color: [255, 0, 0, 255], textures: [[128, 128, 128, 255]; 4], ..Default::default() };
+//! //Opaque, so the default alpha blending keeps all of it
+//! assert_eq!(shade(&state, &inputs, [0; 4]), Some([128, 0, 0, 255]));
+//! ```
+//!
+//! Nothing in here touches the GPU, so it also builds on the host.
+
+pub mod decode;
+pub mod fragment;
+pub mod rasterizer;
+pub mod renderer;
+pub mod shader;
+pub mod texenv;
+
+pub use texenv::Inputs;
+
+use crate::gpucmd::{
+    alpha::{self, Blend, Test},
+    color_operation::BlendMode,
+    logic_op::LogicOp,
+    texenv::UpdateBuffer,
+    texenv_pipeline::TexEnvPipeline,
+};
+
+///`[r,g,b,a]`
+pub type Rgba = [u8; 4];
+
+///Everything that decides the color of a fragment
+#[derive(Clone, Copy)]
+pub struct State {
+    pub pipeline: TexEnvPipeline,
+    pub update_buffer: UpdateBuffer,
+    ///`texenv::BufferColor`
+    pub buffer_color: Rgba,
+    pub alpha_test: Test,
+    pub blend_mode: BlendMode,
+    pub blend: Blend,
+    ///`alpha::Color`
+    pub blend_color: Rgba,
+    pub logic_op: LogicOp,
+}
+
+impl Default for State {
+    ///Passthrough texenv, no alpha test, and the usual alpha blending
+    fn default() -> Self {
+        State {
+            pipeline: TexEnvPipeline::new(),
+            update_buffer: UpdateBuffer::default(),
+            buffer_color: [0xFF; 4],
+            alpha_test: Test::disabled(),
+            blend_mode: BlendMode::Blend,
+            blend: Blend::new(
+                alpha::Add,
+                alpha::Add,
+                alpha::SrcAlpha,
+                alpha::OneMinusSrcAlpha,
+                alpha::SrcAlpha,
+                alpha::OneMinusSrcAlpha,
+            ),
+            blend_color: [0; 4],
+            logic_op: LogicOp::Copy,
+        }
+    }
+}
+
+///The new color buffer value for a fragment over `dst`, or `None` if the alpha test discards it
+pub fn shade(state: &State, inputs: &Inputs, dst: Rgba) -> Option<Rgba> {
+    let color = texenv::combine(&state.pipeline, state.update_buffer, state.buffer_color, inputs);
+    if !fragment::alpha_test(&state.alpha_test, color[3]) {
+        return None;
+    }
+    Some(match state.blend_mode {
+        BlendMode::Blend => fragment::blend(&state.blend, color, dst, state.blend_color),
+        BlendMode::LogicOp => fragment::logic_op(state.logic_op, color, dst),
+    })
+}
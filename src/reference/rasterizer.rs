@@ -0,0 +1,204 @@
+//! Clipping and rasterizing triangles of shaded vertices, modelled after Citra's software rasterizer.
+//!
+//! Clip space is `-w..=w` for X and Y, and `-w..=0` for Z.
+//! Screen space has `(0,0)` in the bottom left corner.
+
+use super::shader::Vec4;
+
+///Number of output semantics, see `shader_outmap::Component`
+pub const SEMANTICS: usize = 0x18;
+
+///A vertex after the shader, with every output semantic in its slot.
+///Semantics that no output maps to are zero.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct OutputVertex(pub [f32; SEMANTICS]);
+
+impl Default for OutputVertex {
+    fn default() -> Self {
+        OutputVertex([0.0; SEMANTICS])
+    }
+}
+
+impl OutputVertex {
+    pub fn position(&self) -> Vec4 {
+        self.0[0x00..0x04].try_into().unwrap()
+    }
+    pub fn quaternion(&self) -> Vec4 {
+        self.0[0x04..0x08].try_into().unwrap()
+    }
+    pub fn color(&self) -> Vec4 {
+        self.0[0x08..0x0C].try_into().unwrap()
+    }
+    ///`n` is `0..3`
+    pub fn texcoord(&self, n: usize) -> [f32; 2] {
+        let start = [0x0C, 0x0E, 0x16][n];
+        self.0[start..start + 2].try_into().unwrap()
+    }
+    pub fn texcoord0_w(&self) -> f32 {
+        self.0[0x10]
+    }
+    pub fn view(&self) -> [f32; 3] {
+        self.0[0x12..0x15].try_into().unwrap()
+    }
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        OutputVertex(std::array::from_fn(|i| self.0[i] + (other.0[i] - self.0[i]) * t))
+    }
+}
+
+///`GPUREG_VIEWPORT_WIDTH`, `HEIGHT` and `XY`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Viewport {
+    pub half_width: f32,
+    pub half_height: f32,
+    pub x: f32,
+    pub y: f32,
+}
+
+///`GPUREG_FACECULLING_CONFIG`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Cull {
+    None,
+    ///Culls triangles wound counter clockwise on screen
+    CounterClockwise,
+    ///Culls triangles wound clockwise on screen
+    Clockwise,
+}
+
+///One covered pixel
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Fragment {
+    pub x: u32,
+    pub y: u32,
+    ///Interpolated linearly on screen, before `DEPTHMAP_SCALE` and `OFFSET`
+    pub z_over_w: f32,
+    pub w: f32,
+    ///Interpolated with perspective correction
+    pub vertex: OutputVertex,
+}
+
+///`(coefficients, bias)`, a vertex is inside when `dot(position + bias, coefficients) >= 0`
+const EDGES: [(Vec4, Vec4); 7] = [
+    ([-1.0, 0.0, 0.0, 1.0], [0.0; 4]),
+    ([1.0, 0.0, 0.0, 1.0], [0.0; 4]),
+    ([0.0, -1.0, 0.0, 1.0], [0.0; 4]),
+    ([0.0, 1.0, 0.0, 1.0], [0.0; 4]),
+    ([0.0, 0.0, -1.0, 0.0], [0.0; 4]),
+    ([0.0, 0.0, 1.0, 1.0], [0.0; 4]),
+    //Keeps w positive
+    ([0.0, 0.0, 0.0, 1.0], [0.0, 0.0, 0.0, -0.00001]),
+];
+
+fn distance(vertex: &OutputVertex, (coefficients, bias): &(Vec4, Vec4)) -> f32 {
+    let position = vertex.position();
+    (0..4).map(|i| (position[i] + bias[i]) * coefficients[i]).sum()
+}
+
+///Sutherland-Hodgman against the clip volume and `clip_plane`, producing a convex polygon
+pub fn clip(triangle: [OutputVertex; 3], clip_plane: Option<Vec4>) -> Vec<OutputVertex> {
+    let mut polygon = triangle.to_vec();
+    let user = clip_plane.map(|plane| (plane, [0.0; 4]));
+    for edge in EDGES.iter().chain(&user) {
+        let input = std::mem::take(&mut polygon);
+        for (i, current) in input.iter().enumerate() {
+            let previous = &input[(i + input.len() - 1) % input.len()];
+            let (d_current, d_previous) = (distance(current, edge), distance(previous, edge));
+            if (d_current >= 0.0) != (d_previous >= 0.0) {
+                polygon.push(previous.lerp(current, d_previous / (d_previous - d_current)));
+            }
+            if d_current >= 0.0 {
+                polygon.push(*current);
+            }
+        }
+        if polygon.is_empty() {
+            break;
+        }
+    }
+    polygon
+}
+
+struct ScreenVertex {
+    x: f32,
+    y: f32,
+    z_over_w: f32,
+    inverse_w: f32,
+    vertex: OutputVertex,
+}
+
+fn to_screen(vertex: &OutputVertex, viewport: &Viewport) -> ScreenVertex {
+    let [x, y, z, w] = vertex.position();
+    ScreenVertex {
+        x: (x / w + 1.0) * viewport.half_width + viewport.x,
+        y: (y / w + 1.0) * viewport.half_height + viewport.y,
+        z_over_w: z / w,
+        inverse_w: 1.0 / w,
+        vertex: *vertex,
+    }
+}
+
+fn edge(a: &ScreenVertex, b: &ScreenVertex, x: f32, y: f32) -> f32 {
+    (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x)
+}
+
+///Pixels exactly on an edge belong to the triangle if it is a top or left edge
+fn is_top_left(a: &ScreenVertex, b: &ScreenVertex) -> bool {
+    (a.y == b.y && b.x < a.x) || b.y < a.y
+}
+
+///Clips, culls and rasterizes a triangle, calling `fragment` for every covered pixel center in `width`x`height`
+pub fn rasterize(
+    triangle: [OutputVertex; 3],
+    clip_plane: Option<Vec4>,
+    viewport: &Viewport,
+    cull: Cull,
+    width: u32,
+    height: u32,
+    mut fragment: impl FnMut(Fragment),
+) {
+    let polygon: Vec<ScreenVertex> = clip(triangle, clip_plane).iter().map(|v| to_screen(v, viewport)).collect();
+    for i in 1..polygon.len().saturating_sub(1) {
+        let [mut a, mut b, c] = [&polygon[0], &polygon[i], &polygon[i + 1]];
+        let area = edge(a, b, c.x, c.y);
+        let culled = match cull {
+            Cull::None => area == 0.0,
+            Cull::CounterClockwise => area >= 0.0,
+            Cull::Clockwise => area <= 0.0,
+        };
+        if culled {
+            continue;
+        }
+        //Make it counter clockwise, so that inside is positive
+        if area < 0.0 {
+            std::mem::swap(&mut a, &mut b);
+        }
+        let area = area.abs();
+        let min_x = a.x.min(b.x).min(c.x).floor().max(0.0) as u32;
+        let min_y = a.y.min(b.y).min(c.y).floor().max(0.0) as u32;
+        let max_x = (a.x.max(b.x).max(c.x).ceil().max(0.0) as u32).min(width);
+        let max_y = (a.y.max(b.y).max(c.y).ceil().max(0.0) as u32).min(height);
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                let weights = [(b, c), (c, a), (a, b)]
+                    .map(|(from, to)| (edge(from, to, px, py), is_top_left(from, to)));
+                if weights.iter().any(|&(w, top_left)| w < 0.0 || (w == 0.0 && !top_left)) {
+                    continue;
+                }
+                let weights = weights.map(|(w, _)| w / area);
+                let vertices = [a, b, c];
+                let z_over_w = (0..3).map(|i| weights[i] * vertices[i].z_over_w).sum();
+                let inverse_w: f32 = (0..3).map(|i| weights[i] * vertices[i].inverse_w).sum();
+                let perspective: [f32; 3] =
+                    std::array::from_fn(|i| weights[i] * vertices[i].inverse_w / inverse_w);
+                fragment(Fragment {
+                    x,
+                    y,
+                    z_over_w,
+                    w: 1.0 / inverse_w,
+                    vertex: OutputVertex(std::array::from_fn(|s| {
+                        (0..3).map(|i| perspective[i] * vertices[i].vertex.0[s]).sum()
+                    })),
+                });
+            }
+        }
+    }
+}
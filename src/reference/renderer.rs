@@ -0,0 +1,1009 @@
+//! A software PICA that runs command buffers on the host, for golden image tests.
+//!
+//! ```rust,ignore
+//! let mut renderer = Renderer::new(240, 320);
+//! let buf = CommandEncoder::new_in(std::alloc::Global)
+//!     + setup
+//!     + immediate::Draw { mode: primitive::Mode::Triangles, vertices }
+//!     + Finish;
+//! renderer.execute(&buf)?;
+//! assert_eq!(renderer.color.pixel(120, 160), [0xFF, 0, 0, 0xFF]);
+//! ```
+//!
+//! Registers are kept in a simulated register file, draws run the uploaded vertex shader,
+//! then go through `rasterizer`, `texenv` and `fragment`.
+//! Like the hardware, only the outputs enabled in `GPUREG_VSH_OUTMAP_MASK` reach `GPUREG_SH_OUTMAP_Oi`.
+//! Textures always sample level 0 with the magnification filter.
+//! Lighting, fog, gas, stencil, cube and shadow textures, and geometry shaders are not modelled,
+//! and are reported as `Error::Unsupported` when they are enabled.
+//! Addresses in the command buffer are looked up in `memory`, colorbuffer and depthbuffer addresses are ignored.
+
+use std::alloc::Allocator;
+
+use crate::gpucmd::regs::*;
+
+use super::{
+    Rgba, State, fragment,
+    rasterizer::{self, Cull, Fragment, OutputVertex, Viewport},
+    shader::{self, Uniforms, Vec4},
+    texenv::{self, Inputs},
+};
+use crate::{
+    floater::{f24tof32, f24x4tof32x4},
+    gpucmd::{
+        CommandBuffer,
+        alpha::{self, Blend, Equation, Factor, Test},
+        color_operation::BlendMode,
+        logic_op::LogicOp,
+        primitive::Mode,
+        texenv::{AlphaOp, ColorOp, CombineMode, E0, E1, E2, E3, E4, E5, Scale, Source, TexEnv, UpdateBuffer},
+        texenv_pipeline::{Function, Stage, TexEnvPipeline},
+        texunit::{TexUnit, U0, U1, U2},
+    },
+    texture::{Format, etc1, pixel, tile},
+};
+
+use super::decode::{self, Write};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Error {
+    Decode(decode::Error),
+    Shader(shader::Error),
+    ///Nothing was mapped with `Memory::map` at `addr..addr + len`
+    Unmapped { addr: u32, len: usize },
+    ///`value` in `reg` is either invalid, or enables something that isn't modelled
+    Unsupported { reg: u32, value: u32 },
+}
+
+///Stands in for linear memory and VRAM, by physical address
+#[derive(Clone, Default, Debug)]
+pub struct Memory {
+    regions: Vec<(u32, Vec<u8>)>,
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Default::default()
+    }
+    ///Makes `data` readable at physical address `addr`, for vertex, index and texture data
+    pub fn map(&mut self, addr: u32, data: Vec<u8>) {
+        self.regions.push((addr, data));
+    }
+    pub fn read(&self, addr: u32, len: usize) -> Result<&[u8], Error> {
+        self.regions
+            .iter()
+            .rev()
+            .find_map(|(start, data)| {
+                let offset = addr.checked_sub(*start)? as usize;
+                data.get(offset..offset.checked_add(len)?)
+            })
+            .ok_or(Error::Unmapped { addr, len })
+    }
+}
+
+///Host side stand-in for `renderbuffer::ColorBuffer`, linear RGBA8 starting with the top row
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ColorBuffer {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<Rgba>,
+}
+
+impl ColorBuffer {
+    pub fn new(width: u32, height: u32) -> Self {
+        ColorBuffer {
+            width,
+            height,
+            pixels: vec![[0; 4]; (width * height) as usize],
+        }
+    }
+    pub fn fill(&mut self, color: Rgba) {
+        self.pixels.fill(color);
+    }
+    ///`y == 0` is the top row
+    pub fn pixel(&self, x: u32, y: u32) -> Rgba {
+        self.pixels[(y * self.width + x) as usize]
+    }
+    ///For writing out golden images
+    pub fn as_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.pixels)
+    }
+}
+
+fn unsupported<T>(reg: u32, value: u32) -> Result<T, Error> {
+    Err(Error::Unsupported { reg, value })
+}
+
+fn lookup<T: Copy>(table: &[T], reg: u32, value: u32) -> Result<T, Error> {
+    table.get(value as usize).copied().ok_or(Error::Unsupported { reg, value })
+}
+
+const FUNCTIONS: [alpha::Function; 8] = {
+    use alpha::Function::*;
+    [Never, Always, Equal, NotEqual, LessThan, LessThanOrEqual, GreaterThan, GreaterThanOrEqual]
+};
+
+const EQUATIONS: [Equation; 5] = {
+    use Equation::*;
+    [Add, Subtract, ReverseSubtract, Min, Max]
+};
+
+const FACTORS: [Factor; 15] = {
+    use Factor::*;
+    [
+        Zero,
+        One,
+        SrcColor,
+        OneMinusSrcColor,
+        DstColor,
+        OneMinusDstColor,
+        SrcAlpha,
+        OneMinusSrcAlpha,
+        DstAlpha,
+        OneMinusDstAlpha,
+        ConstantColor,
+        OneMinusConstantColor,
+        ConstantAlpha,
+        OneMinusConstantAlpha,
+        SaturatedAlpha,
+    ]
+};
+
+const LOGIC_OPS: [LogicOp; 16] = {
+    use LogicOp::*;
+    [
+        Clear,
+        And,
+        ReverseAnd,
+        Copy,
+        Set,
+        InvertedCopy,
+        Noop,
+        Invert,
+        Nand,
+        Or,
+        Nor,
+        Xor,
+        Equivalent,
+        InvertedAnd,
+        ReverseOr,
+        InvertedOr,
+    ]
+};
+
+const COMBINE_MODES: [CombineMode; 10] = {
+    use CombineMode::*;
+    [
+        Replace,
+        Modulate,
+        Add,
+        AddSigned,
+        Interpolate,
+        Subtract,
+        Dot3RGB,
+        Dot3RGBA,
+        MultiplyThenAdd,
+        AddThenMultiply,
+    ]
+};
+
+const SCALES: [Scale; 3] = [Scale::X1, Scale::X2, Scale::X4];
+
+const MODES: [Mode; 4] = [
+    Mode::Triangles,
+    Mode::TriangleStrip,
+    Mode::TriangleFan,
+    Mode::GeometryPrimitive,
+];
+
+const FORMATS: [Format; 14] = {
+    use Format::*;
+    [RGBA8, RGB8, RGBA5551, RGB565, RGBA4, LA8, HILO8, L8, A8, LA4, L4, A4, ETC1, ETC1A4]
+};
+
+fn source(reg: u32, value: u32) -> Result<Source, Error> {
+    use Source::*;
+    Ok(match value {
+        0 => PrimaryColor,
+        1 => FragmentPrimaryColor,
+        2 => FragmentSecondaryColor,
+        3 => Texture0,
+        4 => Texture1,
+        5 => Texture2,
+        6 => Texture3,
+        13 => PreviousBuffer,
+        14 => Constant,
+        15 => Previous,
+        _ => return unsupported(reg, value),
+    })
+}
+
+fn color_op(reg: u32, value: u32) -> Result<ColorOp, Error> {
+    use ColorOp::*;
+    Ok(match value {
+        0 => SourceColor,
+        1 => OneMinusSourceColor,
+        2 => SourceAlpha,
+        3 => OneMinusSourceAlpha,
+        4 => SourceRed,
+        5 => OneMinusSourceRed,
+        8 => SourceGreen,
+        9 => OneMinusSourceGreen,
+        12 => SourceBlue,
+        13 => OneMinusSourceBlue,
+        _ => return unsupported(reg, value),
+    })
+}
+
+fn alpha_op(reg: u32, value: u32) -> Result<AlphaOp, Error> {
+    use AlphaOp::*;
+    lookup(
+        &[
+            SourceAlpha,
+            OneMinusSourceAlpha,
+            SourceRed,
+            OneMinusSourceRed,
+            SourceGreen,
+            OneMinusSourceGreen,
+            SourceBlue,
+            OneMinusSourceBlue,
+        ],
+        reg,
+        value,
+    )
+}
+
+fn unorm8(x: f32) -> u8 {
+    (x * 255.0) as u8
+}
+
+///Turns triangle lists, strips and fans into triangles, like Citra's primitive assembler
+#[derive(Clone, Copy, Default)]
+struct Assembler {
+    buffer: [OutputVertex; 2],
+    count: usize,
+    index: usize,
+    ready: bool,
+}
+
+impl Assembler {
+    fn push(&mut self, mode: Mode, vertex: OutputVertex) -> Option<[OutputVertex; 3]> {
+        match mode {
+            Mode::Triangles | Mode::GeometryPrimitive => {
+                if self.count < 2 {
+                    self.buffer[self.count] = vertex;
+                    self.count += 1;
+                    None
+                } else {
+                    self.count = 0;
+                    Some([self.buffer[0], self.buffer[1], vertex])
+                }
+            }
+            Mode::TriangleStrip | Mode::TriangleFan => {
+                let triangle = self.ready.then_some([self.buffer[0], self.buffer[1], vertex]);
+                self.buffer[self.index] = vertex;
+                self.ready |= self.index == 1;
+                self.index = if mode == Mode::TriangleStrip { 1 - self.index } else { 1 };
+                triangle
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Wrap {
+    ClampToEdge,
+    ClampToBorder,
+    Repeat,
+    MirroredRepeat,
+}
+
+impl Wrap {
+    ///`None` is the border color
+    fn apply(self, i: i32, size: i32) -> Option<i32> {
+        match self {
+            Wrap::ClampToEdge => Some(i.clamp(0, size - 1)),
+            Wrap::ClampToBorder => (0..size).contains(&i).then_some(i),
+            Wrap::Repeat => Some(i.rem_euclid(size)),
+            Wrap::MirroredRepeat => {
+                let i = i.rem_euclid(2 * size);
+                Some(if i < size { i } else { 2 * size - 1 - i })
+            }
+        }
+    }
+}
+
+///Level 0 of a texture unit, decoded to linear RGBA8 starting with the top row
+struct Texture {
+    width: i32,
+    height: i32,
+    texels: Vec<Rgba>,
+    linear: bool,
+    wrap_s: Wrap,
+    wrap_t: Wrap,
+    border: Rgba,
+}
+
+impl Texture {
+    fn texel(&self, x: i32, y: i32) -> Rgba {
+        match (self.wrap_s.apply(x, self.width), self.wrap_t.apply(y, self.height)) {
+            //Texture coordinates start at the bottom
+            (Some(x), Some(y)) => self.texels[((self.height - 1 - y) * self.width + x) as usize],
+            _ => self.border,
+        }
+    }
+    fn sample(&self, [s, t]: [f32; 2]) -> Rgba {
+        let (u, v) = (s * self.width as f32, t * self.height as f32);
+        if !self.linear {
+            return self.texel(u.floor() as i32, v.floor() as i32);
+        }
+        let (u, v) = (u - 0.5, v - 0.5);
+        let (x, y) = (u.floor() as i32, v.floor() as i32);
+        let (fx, fy) = (u - u.floor(), v - v.floor());
+        let [t00, t10, t01, t11] = [
+            self.texel(x, y),
+            self.texel(x + 1, y),
+            self.texel(x, y + 1),
+            self.texel(x + 1, y + 1),
+        ];
+        std::array::from_fn(|c| {
+            let bottom = t00[c] as f32 * (1.0 - fx) + t10[c] as f32 * fx;
+            let top = t01[c] as f32 * (1.0 - fx) + t11[c] as f32 * fx;
+            (bottom * (1.0 - fy) + top * fy).round() as u8
+        })
+    }
+}
+
+///Everything after rasterization, read from the registers once per draw
+struct FragmentPipeline {
+    state: State,
+    textures: [Option<Texture>; 3],
+    texture2_uses_texcoord1: bool,
+    ///`(include, left, bottom, right, top)`, inclusive
+    scissor: Option<(bool, u32, u32, u32, u32)>,
+    depth_scale: f32,
+    depth_offset: f32,
+    w_buffer: bool,
+    depth_max: u32,
+    depth_test: Option<alpha::Function>,
+    depth_write: bool,
+    color_mask: [bool; 4],
+}
+
+struct ImmediateMode {
+    index: u32,
+    words: Vec<u32>,
+    attributes: Vec<Vec4>,
+}
+
+pub struct Renderer {
+    pub memory: Memory,
+    pub color: ColorBuffer,
+    ///Like `color`, starting with the top row
+    pub depth: Vec<u32>,
+    registers: Vec<u32>,
+    code: Vec<u32>,
+    code_offset: usize,
+    opdescs: [u32; 128],
+    opdesc_offset: usize,
+    uniforms: Uniforms,
+    float_uniform: (u32, bool, Vec<u32>),
+    fixed_attributes: [Vec4; 12],
+    immediate: ImmediateMode,
+    assembler: Assembler,
+    pipeline: Option<FragmentPipeline>,
+}
+
+impl Renderer {
+    ///Renders into a `width`x`height` `ColorBuffer` and depth buffer, both cleared to zero
+    pub fn new(width: u32, height: u32) -> Self {
+        Renderer {
+            memory: Memory::new(),
+            color: ColorBuffer::new(width, height),
+            depth: vec![0; (width * height) as usize],
+            registers: vec![0; 0x300],
+            code: vec![0; 4096],
+            code_offset: 0,
+            opdescs: [0; 128],
+            opdesc_offset: 0,
+            uniforms: Uniforms::default(),
+            float_uniform: (0, false, Vec::new()),
+            fixed_attributes: [[0.0, 0.0, 0.0, 1.0]; 12],
+            immediate: ImmediateMode {
+                index: 0,
+                words: Vec::new(),
+                attributes: Vec::new(),
+            },
+            assembler: Assembler::default(),
+            pipeline: None,
+        }
+    }
+    pub fn register(&self, reg: u32) -> u32 {
+        self.registers.get(reg as usize).copied().unwrap_or(0)
+    }
+    pub fn execute<A: Allocator>(&mut self, buf: &CommandBuffer<A>) -> Result<(), Error> {
+        self.run(&buf.buf)
+    }
+    ///Runs raw command buffer words, everything up to an error has been applied
+    pub fn run(&mut self, words: &[u32]) -> Result<(), Error> {
+        //Memory may have changed since the last run
+        self.pipeline = None;
+        for write in decode::writes(words) {
+            self.write(write.map_err(Error::Decode)?)?;
+        }
+        Ok(())
+    }
+    pub fn write(&mut self, write: Write) -> Result<(), Error> {
+        let reg = write.reg;
+        let Some(old) = self.registers.get(reg as usize) else {
+            return Ok(());
+        };
+        let value = write.apply(*old);
+        self.registers[reg as usize] = value;
+        //Everything below the geometry pipeline configures rasterization and fragments
+        if reg < 0x200 {
+            self.pipeline = None;
+        }
+        let in_transfer = |data: u32| (data..data + 8).contains(&reg);
+        match reg {
+            GPUREG_VSH_CODETRANSFER_CONFIG => self.code_offset = (value & 0xFFF) as usize,
+            _ if in_transfer(GPUREG_VSH_CODETRANSFER_DATA) => {
+                if let Some(word) = self.code.get_mut(self.code_offset) {
+                    *word = value;
+                }
+                self.code_offset += 1;
+            }
+            GPUREG_VSH_OPDESCS_CONFIG => self.opdesc_offset = (value & 0x7F) as usize,
+            _ if in_transfer(GPUREG_VSH_OPDESCS_DATA) => {
+                if let Some(word) = self.opdescs.get_mut(self.opdesc_offset) {
+                    *word = value;
+                }
+                self.opdesc_offset += 1;
+            }
+            GPUREG_VSH_FLOATUNIFORM_CONFIG => self.float_uniform = (value & 0x7F, value >> 31 == 1, Vec::new()),
+            _ if in_transfer(GPUREG_VSH_FLOATUNIFORM_DATA) => {
+                let (index, f32_mode, words) = &mut self.float_uniform;
+                words.push(value);
+                let vector = match (*f32_mode, words.len()) {
+                    //WZYX order
+                    (true, 4) => Some([words[3], words[2], words[1], words[0]].map(f32::from_bits)),
+                    (false, 3) => Some(f24x4tof32x4([words[0], words[1], words[2]])),
+                    _ => None,
+                };
+                if let Some(vector) = vector {
+                    if let Some(uniform) = self.uniforms.f.get_mut(*index as usize) {
+                        *uniform = vector;
+                    }
+                    *index += 1;
+                    words.clear();
+                }
+            }
+            _ if (GPUREG_VSH_INTUNIFORM_I0..GPUREG_VSH_INTUNIFORM_I0 + 4).contains(&reg) => {
+                self.uniforms.i[(reg - GPUREG_VSH_INTUNIFORM_I0) as usize] = value.to_le_bytes();
+            }
+            GPUREG_VSH_BOOLUNIFORM => {
+                self.uniforms.b = std::array::from_fn(|i| (value >> i) & 1 == 1);
+            }
+            GPUREG_FIXEDATTRIB_INDEX => {
+                self.immediate.index = value & 0xF;
+                self.immediate.words.clear();
+                if self.immediate.index == 0xF {
+                    self.immediate.attributes.clear();
+                    self.assembler = Assembler::default();
+                }
+            }
+            _ if (GPUREG_FIXEDATTRIB_DATA0..=GPUREG_FIXEDATTRIB_DATA2).contains(&reg) => {
+                self.immediate.words.push(value);
+                if self.immediate.words.len() == 3 {
+                    let words = std::mem::take(&mut self.immediate.words);
+                    let attribute = f24x4tof32x4([words[0], words[1], words[2]]);
+                    self.immediate_attribute(attribute)?;
+                }
+            }
+            GPUREG_RESTART_PRIMITIVE => self.assembler = Assembler::default(),
+            GPUREG_DRAWARRAYS => self.draw(false)?,
+            GPUREG_DRAWELEMENTS => self.draw(true)?,
+            _ => {}
+        }
+        Ok(())
+    }
+    fn immediate_attribute(&mut self, attribute: Vec4) -> Result<(), Error> {
+        let index = self.immediate.index;
+        if index != 0xF {
+            if let Some(fixed) = self.fixed_attributes.get_mut(index as usize) {
+                *fixed = attribute;
+            }
+            self.immediate.index += 1;
+            return Ok(());
+        }
+        self.immediate.attributes.push(attribute);
+        if self.immediate.attributes.len() as u32 > self.register(GPUREG_VSH_NUM_ATTR) & 0xF {
+            let attributes = std::mem::take(&mut self.immediate.attributes);
+            let vertex = self.shade(&attributes)?;
+            self.assemble(vertex)?;
+        }
+        Ok(())
+    }
+    fn mode(&self) -> Mode {
+        MODES[((self.register(GPUREG_PRIMITIVE_CONFIG) >> 8) & 3) as usize]
+    }
+    fn assemble(&mut self, vertex: OutputVertex) -> Result<(), Error> {
+        let mode = self.mode();
+        match self.assembler.push(mode, vertex) {
+            Some(triangle) => self.draw_triangle(triangle),
+            None => Ok(()),
+        }
+    }
+    ///Runs the vertex shader on one vertex's attributes
+    fn shade(&self, attributes: &[Vec4]) -> Result<OutputVertex, Error> {
+        let permutation = self.register(GPUREG_VSH_ATTRIBUTES_PERMUTATION_LOW) as u64
+            | (self.register(GPUREG_VSH_ATTRIBUTES_PERMUTATION_HIGH) as u64) << 32;
+        let mut inputs = [[0.0; 4]; 16];
+        for (i, attribute) in attributes.iter().enumerate() {
+            inputs[((permutation >> (4 * i)) & 0xF) as usize] = *attribute;
+        }
+        let entrypoint = self.register(GPUREG_VSH_ENTRYPOINT) & 0xFFFF;
+        let outputs = shader::run(&self.code, &self.opdescs, entrypoint, &self.uniforms, &inputs)
+            .map_err(Error::Shader)?;
+        let mask = self.register(GPUREG_VSH_OUTMAP_MASK);
+        let total = self.register(GPUREG_SH_OUTMAP_TOTAL).min(7) as usize;
+        let mut vertex = OutputVertex::default();
+        let enabled = outputs.iter().enumerate().filter(|(i, _)| (mask >> i) & 1 == 1);
+        for (o, (_, output)) in enabled.take(total).enumerate() {
+            let semantics = self.register(GPUREG_SH_OUTMAP_O0 + o as u32).to_le_bytes();
+            for (c, semantic) in semantics.into_iter().enumerate() {
+                if let Some(slot) = vertex.0.get_mut((semantic & 0x1F) as usize) {
+                    *slot = output[c];
+                }
+            }
+        }
+        //Colors are made positive and saturated before interpolation
+        for c in &mut vertex.0[0x08..0x0C] {
+            *c = c.abs().min(1.0);
+        }
+        Ok(vertex)
+    }
+    fn draw(&mut self, indexed: bool) -> Result<(), Error> {
+        let geostage = self.register(GPUREG_GEOSTAGE_CONFIG);
+        if geostage & 3 == 2 {
+            return unsupported(GPUREG_GEOSTAGE_CONFIG, geostage);
+        }
+        let base = self.register(GPUREG_ATTRIBBUFFERS_LOC) << 3;
+        let format = self.register(GPUREG_ATTRIBBUFFERS_FORMAT_LOW) as u64
+            | (self.register(GPUREG_ATTRIBBUFFERS_FORMAT_HIGH) as u64) << 32;
+        let attribute_count = ((format >> 60) + 1) as usize;
+        //Type and component count of attribute `i`
+        let kind = |i: u32| ((format >> (4 * i)) & 3, ((format >> (4 * i + 2)) & 3) + 1);
+        let mut sources = [None; 12];
+        for buffer in 0..12 {
+            let reg = GPUREG_ATTRIBBUFFER0_OFFSET + buffer * 3;
+            let (offset, config1, config2) = (self.register(reg), self.register(reg + 1), self.register(reg + 2));
+            let components = config1 as u64 | ((config2 & 0xFFFF) as u64) << 32;
+            let stride = (config2 >> 16) & 0xFF;
+            let mut position = 0u32;
+            for c in 0..config2 >> 28 {
+                let id = ((components >> (4 * c)) & 0xF) as u32;
+                if id < 12 {
+                    let (ty, elements) = kind(id);
+                    let size = [1, 1, 2, 4][ty as usize];
+                    position = position.next_multiple_of(size);
+                    sources[id as usize] = Some((base + offset + position, stride));
+                    position += size * elements as u32;
+                } else {
+                    //Padding of 4, 8, 12 or 16 bytes
+                    position = position.next_multiple_of(4) + (id - 11) * 4;
+                }
+            }
+        }
+        let index_config = self.register(GPUREG_INDEXBUFFER_CONFIG);
+        let index_addr = base + (index_config & 0x0FFFFFFF);
+        let index_u16 = index_config >> 31 == 1;
+        let first = self.register(GPUREG_VERTEX_OFFSET);
+        self.assembler = Assembler::default();
+        for i in 0..self.register(GPUREG_NUMVERTICES) {
+            let index = match (indexed, index_u16) {
+                (false, _) => first + i,
+                (true, false) => self.memory.read(index_addr + i, 1)?[0] as u32,
+                (true, true) => {
+                    let bytes = self.memory.read(index_addr + 2 * i, 2)?;
+                    u16::from_le_bytes([bytes[0], bytes[1]]) as u32
+                }
+            };
+            let mut attributes = Vec::with_capacity(attribute_count);
+            for (a, (source, fixed)) in sources.iter().zip(&self.fixed_attributes).enumerate().take(attribute_count) {
+                let attribute = if (format >> (48 + a)) & 1 == 1 {
+                    *fixed
+                } else if let Some((addr, stride)) = *source {
+                    let (ty, elements) = kind(a as u32);
+                    self.read_attribute(addr + stride * index, ty, elements as usize)?
+                } else {
+                    [0.0, 0.0, 0.0, 1.0]
+                };
+                attributes.push(attribute);
+            }
+            let vertex = self.shade(&attributes)?;
+            self.assemble(vertex)?;
+        }
+        Ok(())
+    }
+    fn read_attribute(&self, addr: u32, ty: u64, elements: usize) -> Result<Vec4, Error> {
+        let size = [1, 1, 2, 4][ty as usize];
+        let data = self.memory.read(addr, size * elements)?;
+        let mut attribute = [0.0, 0.0, 0.0, 1.0];
+        for (e, bytes) in data.chunks_exact(size).enumerate() {
+            attribute[e] = match ty {
+                0 => bytes[0] as i8 as f32,
+                1 => bytes[0] as f32,
+                2 => i16::from_le_bytes([bytes[0], bytes[1]]) as f32,
+                _ => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            };
+        }
+        Ok(attribute)
+    }
+    fn draw_triangle(&mut self, triangle: [OutputVertex; 3]) -> Result<(), Error> {
+        if self.pipeline.is_none() {
+            self.pipeline = Some(self.fragment_pipeline()?);
+        }
+        let xy = self.register(GPUREG_VIEWPORT_XY);
+        let viewport = Viewport {
+            half_width: f24tof32(self.register(GPUREG_VIEWPORT_WIDTH)),
+            half_height: f24tof32(self.register(GPUREG_VIEWPORT_HEIGHT)),
+            //Signed 10 bit
+            x: ((xy << 22) as i32 >> 22) as f32,
+            y: ((xy << 6) as i32 >> 22) as f32,
+        };
+        let cull = match self.register(GPUREG_FACECULLING_CONFIG) & 3 {
+            1 => Cull::CounterClockwise,
+            2 => Cull::Clockwise,
+            _ => Cull::None,
+        };
+        let clip_plane = (self.register(GPUREG_FRAGOP_CLIP) & 1 == 1).then(|| {
+            std::array::from_fn(|i| f24tof32(self.register(GPUREG_FRAGOP_CLIP_DATA0 + i as u32)))
+        });
+        let Renderer {
+            color,
+            depth,
+            pipeline,
+            ..
+        } = self;
+        let pipeline = pipeline.as_ref().unwrap();
+        let (width, height) = (color.width, color.height);
+        rasterizer::rasterize(triangle, clip_plane, &viewport, cull, width, height, |f| {
+            pipeline.fragment(&f, color, depth)
+        });
+        Ok(())
+    }
+    fn stage(&self, base: u32) -> Result<Stage, Error> {
+        let sources = self.register(base);
+        let operands = self.register(base + 1);
+        let combiner = self.register(base + 2);
+        let scale = self.register(base + 4);
+        let rgb_mode = lookup(&COMBINE_MODES, base + 2, combiner & 0xF)?;
+        let alpha_mode = lookup(&COMBINE_MODES, base + 2, (combiner >> 16) & 0xF)?;
+        let mut rgb = Function { mode: rgb_mode, args: [None; 3] };
+        for i in 0..rgb_mode.arguments() {
+            rgb.args[i] = Some((
+                source(base, (sources >> (4 * i)) & 0xF)?,
+                color_op(base + 1, (operands >> (4 * i)) & 0xF)?,
+            ));
+        }
+        let mut alpha = Function { mode: alpha_mode, args: [None; 3] };
+        for i in 0..alpha_mode.arguments() {
+            alpha.args[i] = Some((
+                source(base, (sources >> (16 + 4 * i)) & 0xF)?,
+                alpha_op(base + 1, (operands >> (12 + 4 * i)) & 0x7)?,
+            ));
+        }
+        Ok(Stage {
+            rgb,
+            alpha,
+            color: self.register(base + 3),
+            scale_rgb: lookup(&SCALES, base + 4, scale & 3)?,
+            scale_alpha: lookup(&SCALES, base + 4, (scale >> 16) & 3)?,
+        })
+    }
+    fn texture<TU: TexUnit>(&self) -> Result<Texture, Error> {
+        let dim = self.register(TU::BASE + 1);
+        let param = self.register(TU::BASE + 2);
+        let (width, height) = ((dim >> 16) & 0x7FF, dim & 0x7FF);
+        if (param >> 28) & 7 != 0 {
+            return unsupported(TU::BASE + 2, param);
+        }
+        let format = lookup(&FORMATS, TU::TYPE, self.register(TU::TYPE) & 0xF)?;
+        let size = (width * height) as usize * format.bitsize() / 8;
+        let data = self.memory.read(self.register(TU::BASE + 4) << 3, size)?;
+        let texels = if format.is_compressed() {
+            let rgba = etc1::decode(data, width, height, format == Format::ETC1A4);
+            bytemuck::cast_slice(&rgba).to_vec()
+        } else {
+            (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| pixel::read(format, data, tile::tiled_index(x, y, width, height)))
+                .collect()
+        };
+        let wraps = [Wrap::ClampToEdge, Wrap::ClampToBorder, Wrap::Repeat, Wrap::MirroredRepeat];
+        Ok(Texture {
+            width: width as i32,
+            height: height as i32,
+            texels,
+            linear: (param >> 1) & 1 == 1,
+            wrap_s: wraps[((param >> 12) & 3) as usize],
+            wrap_t: wraps[((param >> 8) & 3) as usize],
+            border: self.register(TU::BASE).to_le_bytes(),
+        })
+    }
+    fn fragment_pipeline(&self) -> Result<FragmentPipeline, Error> {
+        let reg = |reg: u32| self.register(reg);
+        for (r, bits) in [
+            (GPUREG_LIGHTING_ENABLE0, 1),
+            (GPUREG_STENCIL_TEST, 1),
+            //Fog and gas
+            (GPUREG_TEXENV_UPDATE_BUFFER, 7),
+            //Gas and shadow
+            (GPUREG_COLOR_OPERATION, 0xFF),
+        ] {
+            if reg(r) & bits != 0 {
+                return unsupported(r, reg(r));
+            }
+        }
+        let mut stages = [None; 6];
+        for (stage, base) in stages.iter_mut().zip([E0::BASE, E1::BASE, E2::BASE, E3::BASE, E4::BASE, E5::BASE]) {
+            *stage = Some(self.stage(base)?);
+        }
+        let update = reg(GPUREG_TEXENV_UPDATE_BUFFER);
+        let alpha_test = reg(GPUREG_FRAGOP_ALPHA_TEST);
+        let blend = reg(GPUREG_BLEND_FUNC);
+        let nibble = |shift: u32| (blend >> shift) & 0xF;
+        let state = State {
+            pipeline: TexEnvPipeline::from_stages(stages),
+            update_buffer: UpdateBuffer {
+                rgb: std::array::from_fn(|i| (update >> (8 + i)) & 1 == 1),
+                alpha: std::array::from_fn(|i| (update >> (12 + i)) & 1 == 1),
+            },
+            buffer_color: reg(GPUREG_TEXENV_BUFFER_COLOR).to_le_bytes(),
+            alpha_test: Test {
+                enabled: alpha_test & 1 == 1,
+                function: FUNCTIONS[((alpha_test >> 4) & 7) as usize],
+                reference_value: ((alpha_test >> 8) & 0xFF) as u16,
+            },
+            blend_mode: if (reg(GPUREG_COLOR_OPERATION) >> 8) & 1 == 1 {
+                BlendMode::Blend
+            } else {
+                BlendMode::LogicOp
+            },
+            blend: Blend::new(
+                lookup(&EQUATIONS, GPUREG_BLEND_FUNC, blend & 7)?,
+                lookup(&EQUATIONS, GPUREG_BLEND_FUNC, (blend >> 8) & 7)?,
+                lookup(&FACTORS, GPUREG_BLEND_FUNC, nibble(16))?,
+                lookup(&FACTORS, GPUREG_BLEND_FUNC, nibble(20))?,
+                lookup(&FACTORS, GPUREG_BLEND_FUNC, nibble(24))?,
+                lookup(&FACTORS, GPUREG_BLEND_FUNC, nibble(28))?,
+            ),
+            blend_color: reg(GPUREG_BLEND_COLOR).to_le_bytes(),
+            logic_op: LOGIC_OPS[(reg(GPUREG_LOGIC_OP) & 0xF) as usize],
+        };
+        let config = reg(GPUREG_TEXUNIT_CONFIG);
+        let textures = [
+            (config & 1 != 0).then(|| self.texture::<U0>()).transpose()?,
+            (config & 2 != 0).then(|| self.texture::<U1>()).transpose()?,
+            (config & 4 != 0).then(|| self.texture::<U2>()).transpose()?,
+        ];
+        let scissor_mode = reg(GPUREG_SCISSORTEST_MODE) & 3;
+        let (pos, dim) = (reg(GPUREG_SCISSORTEST_POS), reg(GPUREG_SCISSORTEST_DIM));
+        let mask = reg(GPUREG_DEPTH_COLOR_MASK);
+        let depth_writable = reg(GPUREG_DEPTHBUFFER_WRITE) != 0;
+        Ok(FragmentPipeline {
+            state,
+            textures,
+            texture2_uses_texcoord1: (config >> 13) & 1 == 1,
+            scissor: (scissor_mode != 0).then_some((
+                scissor_mode == 3,
+                pos & 0x3FF,
+                (pos >> 16) & 0x3FF,
+                dim & 0x3FF,
+                (dim >> 16) & 0x3FF,
+            )),
+            depth_scale: f24tof32(reg(GPUREG_DEPTHMAP_SCALE)),
+            depth_offset: f24tof32(reg(GPUREG_DEPTHMAP_OFFSET)),
+            w_buffer: reg(GPUREG_DEPTHMAP_ENABLE) & 1 == 0,
+            depth_max: if reg(GPUREG_DEPTHBUFFER_FORMAT) & 3 == 0 { 0xFFFF } else { 0xFFFFFF },
+            depth_test: (mask & 1 == 1).then_some(FUNCTIONS[((mask >> 4) & 7) as usize]),
+            //Like Citra, depth is only written while the depth test is enabled
+            depth_write: depth_writable && mask & 1 == 1 && (mask >> 12) & 1 == 1,
+            color_mask: if reg(GPUREG_COLORBUFFER_WRITE) == 0 {
+                [false; 4]
+            } else {
+                std::array::from_fn(|c| (mask >> (8 + c)) & 1 == 1)
+            },
+        })
+    }
+}
+
+impl FragmentPipeline {
+    fn fragment(&self, f: &Fragment, color: &mut ColorBuffer, depth: &mut [u32]) {
+        if let Some((include, left, bottom, right, top)) = self.scissor {
+            let inside = (left..=right).contains(&f.x) && (bottom..=top).contains(&f.y);
+            if inside != include {
+                return;
+            }
+        }
+        let sample = |unit: usize, texcoord: usize| {
+            self.textures[unit]
+                .as_ref()
+                .map_or([0; 4], |t| t.sample(f.vertex.texcoord(texcoord)))
+        };
+        let inputs = Inputs {
+            primary_color: f.vertex.color().map(unorm8),
+            fragment_primary_color: [0; 4],
+            fragment_secondary_color: [0; 4],
+            textures: [
+                sample(0, 0),
+                sample(1, 1),
+                sample(2, if self.texture2_uses_texcoord1 { 1 } else { 2 }),
+                [0; 4],
+            ],
+        };
+        let state = &self.state;
+        let src = texenv::combine(&state.pipeline, state.update_buffer, state.buffer_color, &inputs);
+        if !fragment::alpha_test(&state.alpha_test, src[3]) {
+            return;
+        }
+        let index = ((color.height - 1 - f.y) * color.width + f.x) as usize;
+        let mut z = f.z_over_w * self.depth_scale + self.depth_offset;
+        if self.w_buffer {
+            z *= f.w;
+        }
+        let z = (z.clamp(0.0, 1.0) * self.depth_max as f32) as u32;
+        if let Some(function) = self.depth_test
+            && !fragment::compare(function, z, depth[index])
+        {
+            return;
+        }
+        if self.depth_write {
+            depth[index] = z;
+        }
+        let dst = color.pixels[index];
+        let out = match state.blend_mode {
+            BlendMode::Blend => fragment::blend(&state.blend, src, dst, state.blend_color),
+            BlendMode::LogicOp => fragment::logic_op(state.logic_op, src, dst),
+        };
+        color.pixels[index] = std::array::from_fn(|c| if self.color_mask[c] { out[c] } else { dst[c] });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::Global;
+
+    use super::*;
+    use crate::{
+        floater::f32tof24,
+        gpucmd::{
+            CommandEncoder, Finish, GpuCmd, Root,
+            attribute::{Layout, Type},
+            clip_plane::ClipPlane,
+            color_operation::FragmentOutput,
+            depth_color_mask::{self, DepthBufferAccess, DepthColorMask, DepthTest, WriteMask},
+            depth_map::{DepthRange, ZBuffer},
+            immediate, logic_op, mask, misc, primitive, shader_outmap,
+        },
+        shader,
+    };
+
+    ///A raw register write, for registers that don't have a command yet
+    #[derive(Clone, Copy)]
+    struct Reg(u32, u32);
+
+    impl GpuCmd for Reg {
+        type Out = [u32; 2];
+        fn cmd(self) -> Self::Out {
+            [self.1, self.0 | mask(0xF)]
+        }
+    }
+
+    ///Draws the bottom left half of a 16x16 target in red, at depth 0.5, with `depth` setting up the depth test
+    fn draw(depth: impl crate::gpucmd::GpuCmdByMut) -> Renderer {
+        let mut entrypoint = None;
+        let code = {
+            use shader::*;
+            Builder::new()
+                + Label(&mut entrypoint)
+                + mov(o(0).unwrap(), v(0).unwrap())
+                + mov(o(1).unwrap(), v(1).unwrap())
+                + end()
+        };
+        let outmap = {
+            use shader_outmap::*;
+            Root + OutMapTotal(2)
+                + OutMap(0, PositionX, PositionY, PositionZ, PositionW)
+                + OutMap(1, ColorR, ColorG, ColorB, ColorA)
+        };
+        let red = [1.0, 0.0, 0.0, 1.0];
+        let buf = CommandEncoder::new_in(Global)
+            + (code, shader::VSH)
+            + misc::VshEntrypoint(entrypoint.unwrap())
+            + Layout::new().add(0, Type::Float, 4).add(1, Type::Float, 4)
+            + outmap
+            + Reg(GPUREG_VSH_OUTMAP_MASK, 0b11)
+            + primitive::Config {
+                outmap_total_minus_1: 1,
+                primitive_mode: primitive::Mode::Triangles,
+            }
+            + Reg(GPUREG_VIEWPORT_WIDTH, f32tof24(8.0))
+            + Reg(GPUREG_VIEWPORT_HEIGHT, f32tof24(8.0))
+            + Reg(GPUREG_COLORBUFFER_WRITE, 0xF)
+            + FragmentOutput::LogicOp(logic_op::Copy)
+            + ZBuffer(DepthRange(0.0, 1.0))
+            + depth
+            + immediate::Draw {
+                mode: primitive::Mode::Triangles,
+                vertices: [
+                    [[-1.0, -1.0, -0.5, 1.0], red],
+                    [[1.0, -1.0, -0.5, 1.0], red],
+                    [[-1.0, 1.0, -0.5, 1.0], red],
+                ],
+            }
+            + Finish;
+        let mut renderer = Renderer::new(16, 16);
+        renderer.execute(&buf).unwrap();
+        renderer
+    }
+
+    #[test]
+    fn triangle() {
+        let renderer = draw(
+            Root + DepthTest(depth_color_mask::Function::Always, WriteMask::ALL)
+                + DepthBufferAccess { read: true, write: true },
+        );
+        let color = &renderer.color;
+        assert_eq!(color.pixel(0, 15), [255, 0, 0, 255]);
+        assert_eq!(color.pixel(2, 13), [255, 0, 0, 255]);
+        assert_eq!(color.pixel(12, 2), [0; 4]);
+        assert_eq!(color.pixel(15, 0), [0; 4]);
+        let covered = color.pixels.iter().filter(|&&p| p != [0; 4]).count();
+        //The 16 pixels centered on the diagonal are on the top right edge, which the fill rule leaves out
+        assert_eq!(covered, 16 * 17 / 2 - 16);
+        //0.5 of the 16 bit depth buffer
+        assert_eq!(renderer.depth[15 * 16], 0x7FFF);
+        assert_eq!(renderer.depth[15], 0);
+    }
+
+    #[test]
+    fn no_depth_write_without_depth_test() {
+        let mask = DepthColorMask {
+            enabled: false,
+            function: depth_color_mask::Function::Always,
+            red_write: true,
+            green_write: true,
+            blue_write: true,
+            alpha_write: true,
+            depth_write: true,
+        };
+        let renderer = draw(Root + mask + DepthBufferAccess { read: true, write: true });
+        assert_eq!(renderer.color.pixel(0, 15), [255, 0, 0, 255]);
+        assert!(renderer.depth.iter().all(|&z| z == 0));
+    }
+
+    #[test]
+    fn clip_plane() {
+        //Keeps the left half, where -x >= 0
+        let renderer = draw(
+            Root + DepthTest(depth_color_mask::Function::Always, WriteMask::ALL)
+                + DepthBufferAccess { read: true, write: true }
+                + ClipPlane([-1.0, 0.0, 0.0, 0.0]),
+        );
+        let color = &renderer.color;
+        assert_eq!(color.pixel(0, 15), [255, 0, 0, 255]);
+        assert_eq!(color.pixel(7, 15), [255, 0, 0, 255]);
+        assert_eq!(color.pixel(8, 15), [0; 4]);
+        assert_eq!(color.pixel(12, 15), [0; 4]);
+        assert!((0..16).all(|y| (8..16).all(|x| color.pixel(x, y) == [0; 4])));
+        //Subtracting it turns it off again
+        let renderer = draw(
+            Root + DepthTest(depth_color_mask::Function::Always, WriteMask::ALL)
+                + ClipPlane([-1.0, 0.0, 0.0, 0.0])
+                - ClipPlane::default(),
+        );
+        assert_eq!(renderer.color.pixel(12, 15), [255, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,316 @@
+//! An interpreter for vertex shader code, as uploaded by `shader::Builder` or a `DVLP`.
+//!
+//! Follows Citra's interpreter, including `0 * inf == 0`, but computes in `f32` rather than 24 bit floats.
+//! `LITP` and the geometry shader instructions `EMIT` and `SETEMIT` are not supported.
+//! https://www.3dbrew.org/wiki/Shader_Instruction_Set
+
+///XYZW order
+pub type Vec4 = [f32; 4];
+
+///Instructions run for a single vertex before giving up
+pub const MAX_STEPS: usize = 1 << 20;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Error {
+    UnsupportedOpcode { pc: u32, opcode: u32 },
+    ///Relative addressing at `pc` went past the float uniforms
+    UniformOutOfRange { pc: u32 },
+    ///Ran off the end of the code without reaching `END`
+    PcOutOfRange { pc: u32 },
+    ///Did not reach `END` within `MAX_STEPS` instructions
+    TooManySteps,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Uniforms {
+    pub f: [Vec4; 96],
+    ///`[count, start, step, _]`, `LOOP` runs `count + 1` times
+    pub i: [[u8; 4]; 4],
+    pub b: [bool; 16],
+}
+
+impl Default for Uniforms {
+    fn default() -> Self {
+        Uniforms {
+            f: [[0.0; 4]; 96],
+            i: [[0; 4]; 4],
+            b: [false; 16],
+        }
+    }
+}
+
+///`0 * inf` is `0` on the PICA
+fn mul(a: f32, b: f32) -> f32 {
+    let result = a * b;
+    if result.is_nan() && !a.is_nan() && !b.is_nan() {
+        0.0
+    } else {
+        result
+    }
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(&a, &b)| mul(a, b)).sum()
+}
+
+fn compare(op: u32, a: f32, b: f32) -> bool {
+    match op {
+        0 => a == b,
+        1 => a != b,
+        2 => a < b,
+        3 => a <= b,
+        4 => a > b,
+        5 => a >= b,
+        //Unknown, treated as always
+        _ => true,
+    }
+}
+
+struct Frame {
+    start: u32,
+    end: u32,
+    return_to: u32,
+    repeat: u8,
+    increment: u8,
+    is_loop: bool,
+}
+
+struct Unit<'a> {
+    uniforms: &'a Uniforms,
+    opdescs: &'a [u32],
+    input: [Vec4; 16],
+    temp: [Vec4; 16],
+    output: [Vec4; 16],
+    ///`a0.x`, `a0.y` and `aL`
+    address: [i32; 3],
+    condition: [bool; 2],
+}
+
+impl<'a> Unit<'a> {
+    fn read(&self, pc: u32, reg: u32, offset: i32) -> Result<Vec4, Error> {
+        Ok(match reg {
+            0x00..0x10 => self.input[reg as usize],
+            0x10..0x20 => self.temp[reg as usize - 0x10],
+            _ => {
+                let index = reg as i32 - 0x20 + offset;
+                *usize::try_from(index)
+                    .ok()
+                    .and_then(|i| self.uniforms.f.get(i))
+                    .ok_or(Error::UniformOutOfRange { pc })?
+            }
+        })
+    }
+    ///`n` is `0..3`, for the negate and swizzle of source `n + 1`
+    fn source(&self, pc: u32, opdesc: u32, n: u32, reg: u32, offset: i32) -> Result<Vec4, Error> {
+        let value = self.read(pc, reg, offset)?;
+        let shift = [4, 13, 22][n as usize];
+        let negate = (opdesc >> shift) & 1 == 1;
+        let swizzle = (opdesc >> (shift + 1)) & 0xFF;
+        Ok(std::array::from_fn(|c| {
+            let v = value[((swizzle >> (6 - 2 * c)) & 3) as usize];
+            if negate { -v } else { v }
+        }))
+    }
+    fn write(&mut self, opdesc: u32, reg: u32, value: Vec4) {
+        let dest = match reg {
+            0x00..0x10 => &mut self.output[reg as usize],
+            _ => &mut self.temp[(reg & 0xF) as usize],
+        };
+        for c in 0..4 {
+            if (opdesc >> (3 - c)) & 1 == 1 {
+                dest[c] = value[c];
+            }
+        }
+    }
+    fn offset(&self, index: u32) -> i32 {
+        match index {
+            0 => 0,
+            i => self.address[i as usize - 1],
+        }
+    }
+    fn condition(&self, instr: u32) -> bool {
+        let x = self.condition[0] == ((instr >> 25) & 1 == 1);
+        let y = self.condition[1] == ((instr >> 24) & 1 == 1);
+        match (instr >> 22) & 3 {
+            0 => x || y,
+            1 => x && y,
+            2 => x,
+            _ => y,
+        }
+    }
+}
+
+///Runs `code` from `entrypoint` until `END`, returning the output registers.
+///`inputs` are the input registers, after the attribute permutation.
+pub fn run(
+    code: &[u32],
+    opdescs: &[u32],
+    entrypoint: u32,
+    uniforms: &Uniforms,
+    inputs: &[Vec4; 16],
+) -> Result<[Vec4; 16], Error> {
+    let mut unit = Unit {
+        uniforms,
+        opdescs,
+        input: *inputs,
+        temp: [[0.0; 4]; 16],
+        output: [[0.0; 4]; 16],
+        address: [0; 3],
+        condition: [false; 2],
+    };
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut pc = entrypoint;
+    for _ in 0..MAX_STEPS {
+        while let Some(top) = stack.last_mut() {
+            if pc != top.end {
+                break;
+            }
+            unit.address[2] += top.increment as i32;
+            if top.repeat == 0 {
+                pc = top.return_to;
+                stack.pop();
+            } else {
+                top.repeat -= 1;
+                pc = top.start;
+            }
+        }
+        let instr = *code.get(pc as usize).ok_or(Error::PcOutOfRange { pc })?;
+        let opcode = instr >> 26;
+        let mut next = pc + 1;
+        //Flow control
+        let num = instr & 0xFF;
+        let dest = (instr >> 10) & 0xFFF;
+        let bool_uniform = uniforms.b[((instr >> 22) & 0xF) as usize];
+        let mut call = |start: u32, len: u32, return_to: u32, repeat: u8, increment: u8, is_loop: bool| {
+            stack.push(Frame {
+                start,
+                end: start + len,
+                return_to,
+                repeat,
+                increment,
+                is_loop,
+            });
+            start
+        };
+        match opcode {
+            //Arithmetic, with the 7 bit source first unless inverted
+            0x00..=0x13 | 0x18..=0x1B => {
+                let opdesc = unit.opdescs.get((instr & 0x7F) as usize).copied().unwrap_or(0);
+                let offset = unit.offset((instr >> 19) & 3);
+                let (s1, s2) = if opcode >= 0x18 {
+                    (
+                        unit.source(pc, opdesc, 0, (instr >> 14) & 0x1F, 0)?,
+                        unit.source(pc, opdesc, 1, (instr >> 7) & 0x7F, offset)?,
+                    )
+                } else {
+                    (
+                        unit.source(pc, opdesc, 0, (instr >> 12) & 0x7F, offset)?,
+                        unit.source(pc, opdesc, 1, (instr >> 7) & 0x1F, 0)?,
+                    )
+                };
+                let dst = (instr >> 21) & 0x1F;
+                let result = match opcode {
+                    0x00 => std::array::from_fn(|c| s1[c] + s2[c]),
+                    0x01 => [dot(&s1[..3], &s2[..3]); 4],
+                    0x02 => [dot(&s1, &s2); 4],
+                    0x03 | 0x18 => [dot(&s1[..3], &s2[..3]) + s2[3]; 4],
+                    0x04 | 0x19 => [1.0, mul(s1[1], s2[1]), s1[2], s2[3]],
+                    0x05 => [s1[0].exp2(); 4],
+                    0x06 => [s1[0].log2(); 4],
+                    0x08 => std::array::from_fn(|c| mul(s1[c], s2[c])),
+                    0x09 | 0x1A => std::array::from_fn(|c| if s1[c] >= s2[c] { 1.0 } else { 0.0 }),
+                    0x0A | 0x1B => std::array::from_fn(|c| if s1[c] < s2[c] { 1.0 } else { 0.0 }),
+                    0x0B => s1.map(f32::floor),
+                    0x0C => std::array::from_fn(|c| if s1[c] > s2[c] { s1[c] } else { s2[c] }),
+                    0x0D => std::array::from_fn(|c| if s1[c] < s2[c] { s1[c] } else { s2[c] }),
+                    0x0E => [1.0 / s1[0]; 4],
+                    0x0F => [1.0 / s1[0].sqrt(); 4],
+                    0x12 => {
+                        for (c, (address, value)) in unit.address.iter_mut().zip(s1).enumerate().take(2) {
+                            if (opdesc >> (3 - c)) & 1 == 1 {
+                                *address = value as i32;
+                            }
+                        }
+                        pc = next;
+                        continue;
+                    }
+                    0x13 => s1,
+                    _ => return Err(Error::UnsupportedOpcode { pc, opcode }),
+                };
+                unit.write(opdesc, dst, result);
+            }
+            0x20 | 0x23 => {
+                if opcode == 0x20 || unit.condition(instr) {
+                    while let Some(frame) = stack.pop() {
+                        if frame.is_loop {
+                            next = frame.return_to;
+                            break;
+                        }
+                    }
+                }
+            }
+            0x21 => {}
+            0x22 => return Ok(unit.output),
+            0x24 => next = call(dest, num, pc + 1, 0, 0, false),
+            0x25 | 0x26 => {
+                if (opcode == 0x25 && unit.condition(instr)) || (opcode == 0x26 && bool_uniform) {
+                    next = call(dest, num, pc + 1, 0, 0, false);
+                }
+            }
+            0x27 | 0x28 => {
+                let taken = if opcode == 0x27 { bool_uniform } else { unit.condition(instr) };
+                next = if taken {
+                    call(pc + 1, dest.wrapping_sub(pc + 1), dest + num, 0, 0, false)
+                } else {
+                    call(dest, num, dest + num, 0, 0, false)
+                };
+            }
+            0x29 => {
+                let [count, start, step, _] = uniforms.i[((instr >> 22) & 3) as usize];
+                unit.address[2] = start as i32;
+                next = call(pc + 1, dest.wrapping_sub(pc), dest + 1, count, step, true);
+            }
+            0x2C => {
+                if unit.condition(instr) {
+                    next = dest;
+                }
+            }
+            0x2D => {
+                if bool_uniform == (num & 1 == 0) {
+                    next = dest;
+                }
+            }
+            0x2E | 0x2F => {
+                let opdesc = unit.opdescs.get((instr & 0x7F) as usize).copied().unwrap_or(0);
+                let offset = unit.offset((instr >> 19) & 3);
+                let s1 = unit.source(pc, opdesc, 0, (instr >> 12) & 0x7F, offset)?;
+                let s2 = unit.source(pc, opdesc, 1, (instr >> 7) & 0x1F, 0)?;
+                unit.condition = [
+                    compare((instr >> 24) & 7, s1[0], s2[0]),
+                    compare((instr >> 21) & 7, s1[1], s2[1]),
+                ];
+            }
+            0x30..=0x3F => {
+                let opdesc = unit.opdescs.get((instr & 0x1F) as usize).copied().unwrap_or(0);
+                let offset = unit.offset((instr >> 22) & 3);
+                let s1 = unit.source(pc, opdesc, 0, (instr >> 17) & 0x1F, 0)?;
+                let (s2, s3) = if opcode >= 0x38 {
+                    (
+                        unit.source(pc, opdesc, 1, (instr >> 10) & 0x7F, offset)?,
+                        unit.source(pc, opdesc, 2, (instr >> 5) & 0x1F, 0)?,
+                    )
+                } else {
+                    (
+                        unit.source(pc, opdesc, 1, (instr >> 12) & 0x1F, 0)?,
+                        unit.source(pc, opdesc, 2, (instr >> 5) & 0x7F, offset)?,
+                    )
+                };
+                let result = std::array::from_fn(|c| mul(s1[c], s2[c]) + s3[c]);
+                unit.write(opdesc, (instr >> 24) & 0x1F, result);
+            }
+            _ => return Err(Error::UnsupportedOpcode { pc, opcode }),
+        }
+        pc = next;
+    }
+    Err(Error::TooManySteps)
+}
@@ -0,0 +1,263 @@
+//! The six texenv stages, modelled after Citra's software rasterizer.
+
+use super::Rgba;
+use crate::gpucmd::{
+    texenv::{AlphaOp, ColorOp, CombineMode, Scale, Source, UpdateBuffer},
+    texenv_pipeline::{Stage, TexEnvPipeline},
+};
+
+///Everything a fragment can read in a texenv stage
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Inputs {
+    ///Interpolated vertex color
+    pub primary_color: Rgba,
+    ///Diffuse and ambient lighting
+    pub fragment_primary_color: Rgba,
+    ///Specular lighting
+    pub fragment_secondary_color: Rgba,
+    ///Samples of texture units `0..=3`
+    pub textures: [Rgba; 4],
+}
+
+///Runs every stage, unset stages pass the previous one through like `texenv::default_for`
+pub fn combine(
+    pipeline: &TexEnvPipeline,
+    update_buffer: UpdateBuffer,
+    buffer_color: Rgba,
+    inputs: &Inputs,
+) -> Rgba {
+    let mut previous = [0; 4];
+    //The buffer lags behind by one stage: stage 0 reads zero, stage 1 the buffer color,
+    //and what stage `i` writes shows up from stage `i + 2` on
+    let mut buffer = [0; 4];
+    let mut next_buffer = buffer_color;
+    for (index, stage) in pipeline.stages().iter().enumerate() {
+        let stage = stage.unwrap_or_else(Stage::passthrough);
+        let output = stage_output(&stage, inputs, previous, buffer);
+        buffer = next_buffer;
+        if index < 4 {
+            if update_buffer.rgb[index] {
+                next_buffer[..3].copy_from_slice(&output[..3]);
+            }
+            if update_buffer.alpha[index] {
+                next_buffer[3] = output[3];
+            }
+        }
+        previous = output;
+    }
+    previous
+}
+
+fn source(source: Source, inputs: &Inputs, previous: Rgba, buffer: Rgba, constant: Rgba) -> Rgba {
+    match source {
+        Source::PrimaryColor => inputs.primary_color,
+        Source::FragmentPrimaryColor => inputs.fragment_primary_color,
+        Source::FragmentSecondaryColor => inputs.fragment_secondary_color,
+        Source::Texture0 => inputs.textures[0],
+        Source::Texture1 => inputs.textures[1],
+        Source::Texture2 => inputs.textures[2],
+        Source::Texture3 => inputs.textures[3],
+        Source::PreviousBuffer => buffer,
+        Source::Constant => constant,
+        Source::Previous => previous,
+    }
+}
+
+fn color_op(op: ColorOp, [r, g, b, a]: Rgba) -> [u8; 3] {
+    use ColorOp::*;
+    match op {
+        SourceColor => [r, g, b],
+        OneMinusSourceColor => [255 - r, 255 - g, 255 - b],
+        SourceAlpha => [a; 3],
+        OneMinusSourceAlpha => [255 - a; 3],
+        SourceRed => [r; 3],
+        OneMinusSourceRed => [255 - r; 3],
+        SourceGreen => [g; 3],
+        OneMinusSourceGreen => [255 - g; 3],
+        SourceBlue => [b; 3],
+        OneMinusSourceBlue => [255 - b; 3],
+    }
+}
+
+fn alpha_op(op: AlphaOp, [r, g, b, a]: Rgba) -> u8 {
+    use AlphaOp::*;
+    match op {
+        SourceAlpha => a,
+        OneMinusSourceAlpha => 255 - a,
+        SourceRed => r,
+        OneMinusSourceRed => 255 - r,
+        SourceGreen => g,
+        OneMinusSourceGreen => 255 - g,
+        SourceBlue => b,
+        OneMinusSourceBlue => 255 - b,
+    }
+}
+
+///One channel of every mode but the dot products
+fn apply(mode: CombineMode, a: u8, b: u8, c: u8) -> u8 {
+    let (a, b, c) = (a as u32, b as u32, c as u32);
+    use CombineMode::*;
+    let result = match mode {
+        Replace => a,
+        Modulate => a * b / 255,
+        Add => a + b,
+        AddSigned => (a + b).saturating_sub(128),
+        Interpolate => (a * c + b * (255 - c)) / 255,
+        Subtract => a.saturating_sub(b),
+        MultiplyThenAdd => a * b / 255 + c,
+        AddThenMultiply => (a + b).min(255) * c / 255,
+        Dot3RGB | Dot3RGBA => unreachable!("Dot products combine all channels"),
+    };
+    result.min(255) as u8
+}
+
+fn dot3(a: [u8; 3], b: [u8; 3]) -> u8 {
+    let sum: i32 = (0..3)
+        .map(|i| (a[i] as i32 * 2 - 255) * (b[i] as i32 * 2 - 255))
+        .sum();
+    ((sum + 128) >> 8).clamp(0, 255) as u8
+}
+
+fn scale(value: u8, scale: Scale) -> u8 {
+    let factor = match scale {
+        Scale::X1 => 1,
+        Scale::X2 => 2,
+        Scale::X4 => 4,
+    };
+    (value as u32 * factor).min(255) as u8
+}
+
+fn stage_output(stage: &Stage, inputs: &Inputs, previous: Rgba, buffer: Rgba) -> Rgba {
+    let constant = stage.color.to_le_bytes();
+    let fetch = |s: Source| source(s, inputs, previous, buffer, constant);
+    let rgb_args = stage
+        .rgb
+        .args
+        .map(|arg| arg.map_or([0; 3], |(s, op)| color_op(op, fetch(s))));
+    let alpha_args = stage
+        .alpha
+        .args
+        .map(|arg| arg.map_or(0, |(s, op)| alpha_op(op, fetch(s))));
+    let rgb = match stage.rgb.mode {
+        CombineMode::Dot3RGB | CombineMode::Dot3RGBA => [dot3(rgb_args[0], rgb_args[1]); 3],
+        mode => std::array::from_fn(|c| apply(mode, rgb_args[0][c], rgb_args[1][c], rgb_args[2][c])),
+    };
+    let alpha = match (stage.rgb.mode, stage.alpha.mode) {
+        //Dot3RGBA writes the dot product to alpha as well
+        (CombineMode::Dot3RGBA, _) => rgb[0],
+        (_, CombineMode::Dot3RGB | CombineMode::Dot3RGBA) => dot3([alpha_args[0]; 3], [alpha_args[1]; 3]),
+        (_, mode) => apply(mode, alpha_args[0], alpha_args[1], alpha_args[2]),
+    };
+    [
+        scale(rgb[0], stage.scale_rgb),
+        scale(rgb[1], stage.scale_rgb),
+        scale(rgb[2], stage.scale_rgb),
+        scale(alpha, stage.scale_alpha),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gpucmd::texenv_pipeline::Function;
+
+    const A: Rgba = [200, 100, 50, 200];
+    const B: Rgba = [40, 60, 250, 30];
+    const C: Rgba = [64, 128, 255, 64];
+
+    ///Reads `Texture0..=2` as arguments, as many as each mode takes
+    fn function<Op: Copy>(mode: CombineMode, op: Op) -> Function<Op> {
+        let sources = [Source::Texture0, Source::Texture1, Source::Texture2];
+        Function {
+            mode,
+            args: std::array::from_fn(|i| (i < mode.arguments()).then_some((sources[i], op))),
+        }
+    }
+
+    fn run(pipeline: TexEnvPipeline, inputs: [Rgba; 3]) -> Rgba {
+        let inputs = Inputs {
+            textures: [inputs[0], inputs[1], inputs[2], [0; 4]],
+            ..Default::default()
+        };
+        combine(&pipeline, UpdateBuffer::default(), [0; 4], &inputs)
+    }
+
+    fn mode(rgb: CombineMode, alpha: CombineMode, inputs: [Rgba; 3]) -> Rgba {
+        let stage = Stage::new(function(rgb, ColorOp::SourceColor), function(alpha, AlphaOp::SourceAlpha));
+        run(TexEnvPipeline::new().push(stage).unwrap(), inputs)
+    }
+
+    #[test]
+    fn combine_modes() {
+        use CombineMode::*;
+        for (m, expected) in [
+            (Replace, A),
+            (Modulate, [31, 23, 49, 23]),
+            (Add, [240, 160, 255, 230]),
+            (AddSigned, [112, 32, 172, 102]),
+            (Interpolate, [80, 80, 50, 72]),
+            (Subtract, [160, 40, 0, 170]),
+            (MultiplyThenAdd, [95, 151, 255, 87]),
+            (AddThenMultiply, [60, 80, 255, 57]),
+        ] {
+            assert_eq!(mode(m, m, [A, B, C]), expected, "{m:?}");
+        }
+    }
+
+    #[test]
+    fn dot3() {
+        use CombineMode::*;
+        let up = [255, 128, 128, 0];
+        let down = [0, 128, 128, 0];
+        assert_eq!(mode(Dot3RGB, Replace, [up, up, C]), [254, 254, 254, 0]);
+        assert_eq!(mode(Dot3RGB, Replace, [up, down, C]), [0, 0, 0, 0]);
+        //Also writes the dot product to alpha, whatever the alpha combiner is
+        assert_eq!(mode(Dot3RGBA, Replace, [up, up, C]), [254; 4]);
+    }
+
+    #[test]
+    fn operands() {
+        let stage = Stage::new(
+            Function::replace((Source::Texture0, ColorOp::OneMinusSourceAlpha)),
+            Function::replace((Source::Texture0, AlphaOp::SourceGreen)),
+        );
+        assert_eq!(run(TexEnvPipeline::new().push(stage).unwrap(), [A, B, C]), [55, 55, 55, 100]);
+    }
+
+    #[test]
+    fn scale() {
+        let stage = Stage {
+            scale_rgb: Scale::X2,
+            scale_alpha: Scale::X4,
+            ..Stage::replace(Source::Texture1)
+        };
+        assert_eq!(run(TexEnvPipeline::new().push(stage).unwrap(), [A, B, C]), [80, 120, 255, 120]);
+    }
+
+    #[test]
+    fn unset_stages_pass_through() {
+        let pipeline = TexEnvPipeline::new().set(0, Stage::replace(Source::Texture2)).unwrap();
+        assert_eq!(run(pipeline, [A, B, C]), C);
+    }
+
+    #[test]
+    fn buffer_lags_one_stage() {
+        let pipeline = TexEnvPipeline::new()
+            .push(Stage::replace(Source::Texture0))
+            .and_then(|p| p.push(Stage::replace(Source::PreviousBuffer)))
+            .unwrap();
+        let update_buffer = UpdateBuffer {
+            rgb: [true, false, false, false],
+            alpha: [true, false, false, false],
+        };
+        let inputs = Inputs {
+            textures: [A, B, C, [0; 4]],
+            ..Default::default()
+        };
+        //Stage 1 still reads the buffer color
+        assert_eq!(combine(&pipeline, update_buffer, B, &inputs), B);
+        //Stage 2 reads what stage 0 wrote
+        let pipeline = pipeline.push(Stage::replace(Source::PreviousBuffer)).unwrap();
+        assert_eq!(combine(&pipeline, update_buffer, B, &inputs), A);
+    }
+}
@@ -60,7 +60,8 @@ impl ColorBuffer {
 
 impl GpuCmdByMut for &ColorBuffer {
     fn cmd_by_mut<A:std::alloc::Allocator>(self, buf: &mut Vec<u32,A>) {
-        use ctru_sys::*;
+        use crate::gpucmd::regs::*;
+        use ctru_sys::osConvertVirtToPhys;
         buf.extend_from_slice(&[
             self.format.gpureg_param(),
             GPUREG_COLORBUFFER_FORMAT | mask(0xF),
@@ -107,7 +107,7 @@ impl crate::gpucmd::GpuCmdByMut for (Builder,VSH) {
     fn cmd_by_mut<A:std::alloc::Allocator>(self, buf: &mut Vec<u32,A>) {
         use crate::gpucmd::transfer::Transfer;
         use crate::gpucmd::mask;
-        use ctru_sys::{GPUREG_VSH_CODETRANSFER_CONFIG,GPUREG_VSH_CODETRANSFER_DATA,GPUREG_VSH_CODETRANSFER_END,GPUREG_VSH_OPDESCS_CONFIG,GPUREG_VSH_OPDESCS_DATA};
+        use crate::gpucmd::regs::{GPUREG_VSH_CODETRANSFER_CONFIG,GPUREG_VSH_CODETRANSFER_DATA,GPUREG_VSH_CODETRANSFER_END,GPUREG_VSH_OPDESCS_CONFIG,GPUREG_VSH_OPDESCS_DATA};
         let this = self.0;
         buf.extend_from_slice(&[
             0,
@@ -127,7 +127,8 @@ impl crate::gpucmd::GpuCmdByMut for (Builder,VSH) {
         ]);
         Transfer {
             reg: GPUREG_VSH_OPDESCS_DATA | mask(0xF),
-            data: bytemuck::cast_slice(&this.opdesc)
+            //One word per operand descriptor, the upper half of each is unused
+            data: this.opdesc.iter().map(|&d| d as u32).collect::<Vec<u32>>()
         }.cmd_by_mut(buf);
     }
 }
@@ -371,7 +372,7 @@ impl AddToBuilder for Format1u {
         let opdesc = b.add_opdesc(opdesc);
         b.prog.push(
             0 | opdesc
-            | (self.source1.reg << 0x7)
+            | (self.source1.reg << 0xC)
             | ((self.source1_addr as u32) << 0x13)
             | (self.dest.reg << 0x15)
             | (self.opcode << 0x1A)
@@ -4,6 +4,16 @@ use crate::gpucmd::shader_outmap::OutMap;
 
 use super::{Error::UnexpectedEof as EOF, GshMode, Kind};
 
+//Output types, from libctru's `shbin.h`
+const RESULT_POSITION: u8 = 0;
+const RESULT_NORMALQUAT: u8 = 1;
+const RESULT_COLOR: u8 = 2;
+const RESULT_TEXCOORD0: u8 = 3;
+const RESULT_TEXCOORD0W: u8 = 4;
+const RESULT_TEXCOORD1: u8 = 5;
+const RESULT_TEXCOORD2: u8 = 6;
+const RESULT_VIEW: u8 = 8;
+
 pub struct DVLE {
     ///If this is set, this is a Geometry Shader
     pub(crate) geom: Option<DVLEGeom>,
@@ -127,7 +137,7 @@ impl DVLE {
         //Don't forget to merge them if requested
         let mut outmap = [0x1F1F1F1Fu32; 10];
         let mut outmap_total = 0u32;
-        outmap[1] = ctru_sys::GPUREG_SH_OUTMAP_TOTAL
+        outmap[1] = crate::gpucmd::regs::GPUREG_SH_OUTMAP_TOTAL
             | crate::gpucmd::CONSECUTIVE_WRITING
             | crate::gpucmd::extra_params(7);
         //https://github.com/devkitPro/libctru/blob/master/libctru/source/gpu/shaderProgram.c
@@ -150,7 +160,6 @@ impl DVLE {
             }
             let mut sem = 0x1F;
             let mut num = 0;
-            use ctru_sys::*;
             match *kind as u8 {
                 // case RESULT_POSITION:   sem = 0x00; num = 4;                                                     break;
                 RESULT_POSITION => {
@@ -43,7 +43,7 @@ impl DVLP {
 
 impl GpuCmdByMut for (VSH,&DVLP) {
     fn cmd_by_mut<A:std::alloc::Allocator>(self, buf: &mut Vec<u32,A>) {
-        use ctru_sys::*;
+        use crate::gpucmd::regs::*;
         // buf.push([0,GPUREG_VSH_CODETRANSFER_CONFIG | mask(0xf)]);
 
     }
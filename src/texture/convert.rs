@@ -0,0 +1,121 @@
+//! Converting RGBA8 images into every `Format`, for baking assets ahead of time.
+//!
+//! Like `tile`, `pixel` and `etc1` this doesn't touch the GPU, so it also builds on the host, for example in a build script.
+
+use super::{Format, etc1, pixel, tile};
+
+///4x4 Bayer matrix
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+///Bits kept of each of R, G, B and A, 8 meaning the channel isn't quantized.
+///Luminance formats use the R, G and B entries for luminance.
+pub fn channel_bits(format: Format) -> [u32; 4] {
+    use Format::*;
+    match format {
+        RGB565 => [5, 6, 5, 8],
+        RGBA5551 => [5, 5, 5, 1],
+        RGBA4 => [4; 4],
+        LA4 => [4; 4],
+        L4 => [4, 4, 4, 8],
+        A4 => [8, 8, 8, 4],
+        _ => [8; 4],
+    }
+}
+
+///`rgba` is linear RGBA8, the result is linear and in `format`.
+///With `dither` the low bit formats get ordered dithering instead of plain rounding.
+///Panics on `ETC1` and `ETC1A4`, use `etc1::encode` or `bake` for those.
+pub fn convert(rgba: &[u8], width: u32, height: u32, format: Format, dither: bool) -> Vec<u8> {
+    let bits = channel_bits(format);
+    let mut out = vec![0; (width * height) as usize * format.bitsize() / 8];
+    for y in 0..height {
+        for x in 0..width {
+            let index = (y * width + x) as usize;
+            let mut color: [u8; 4] = rgba[index * 4..][..4].try_into().unwrap();
+            if dither {
+                let threshold = (BAYER[(y % 4) as usize][(x % 4) as usize] as f32 + 0.5) / 16.0 - 0.5;
+                for c in 0..4 {
+                    if bits[c] < 8 {
+                        let step = 255.0 / ((1 << bits[c]) - 1) as f32;
+                        color[c] = (color[c] as f32 + threshold * step).round().clamp(0.0, 255.0) as u8;
+                    }
+                }
+            }
+            pixel::write(format, &mut out, index, color);
+        }
+    }
+    out
+}
+
+///Converts and tiles `rgba`, giving the data as `Texture` stores it for mip level 0.
+///Upload the result with `Texture::upload_raw`, the other uploads would tile it again.
+///`ETC1` and `ETC1A4` are encoded with `etc1::encode`, which ignores `dither`.
+pub fn bake(rgba: &[u8], width: u32, height: u32, format: Format, dither: bool) -> Vec<u8> {
+    match format {
+        Format::ETC1 => etc1::encode(rgba, width, height, false),
+        Format::ETC1A4 => etc1::encode(rgba, width, height, true),
+        _ => {
+            let linear = convert(rgba, width, height, format, dither);
+            let mut tiled = vec![0; linear.len()];
+            tile::tile(&linear, &mut tiled, width, height, format.bitsize());
+            tiled
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Format::*;
+
+    const PIXELS: [u8; 8] = [255, 128, 0, 255, 0x12, 0x34, 0x56, 0x80];
+
+    #[test]
+    fn formats() {
+        let expected: [(Format, &[u8]); 10] = [
+            (RGB565, &[0x00, 0xFC, 0xAA, 0x11]),
+            (RGBA5551, &[0x01, 0xFC, 0x95, 0x11]),
+            (RGBA4, &[0x0F, 0xF8, 0x58, 0x13]),
+            //Luminance is 0x98 and 0x2E
+            (LA8, &[0xFF, 0x98, 0x80, 0x2E]),
+            (HILO8, &[0x80, 0xFF, 0x34, 0x12]),
+            (L8, &[0x98, 0x2E]),
+            (A8, &[0xFF, 0x80]),
+            (LA4, &[0x9F, 0x38]),
+            //The first pixel in the low nibble
+            (L4, &[0x39]),
+            (A4, &[0x8F]),
+        ];
+        for (i, (format, expected)) in expected.into_iter().enumerate() {
+            assert_eq!(convert(&PIXELS, 2, 1, format, false), expected, "format {i}");
+        }
+    }
+
+    const ALL: [Format; 12] = [RGBA8, RGB8, RGBA5551, RGB565, RGBA4, LA8, HILO8, L8, A8, LA4, L4, A4];
+
+    #[test]
+    fn dither_keeps_flat_extremes() {
+        for (i, format) in ALL.into_iter().enumerate() {
+            for value in [0x00, 0xFF] {
+                let flat = [value; 4 * 4 * 4];
+                assert_eq!(convert(&flat, 4, 4, format, true), convert(&flat, 4, 4, format, false), "format {i}");
+            }
+        }
+    }
+
+    #[test]
+    fn dither_only_low_bit_formats() {
+        let gradient: Vec<u8> = (0..4 * 4 * 4).map(|i| (i * 4 + 1) as u8).collect();
+        for (i, format) in ALL.into_iter().enumerate() {
+            let dithered = convert(&gradient, 4, 4, format, true) != convert(&gradient, 4, 4, format, false);
+            assert_eq!(dithered, channel_bits(format) != [8; 4], "format {i}");
+        }
+        //A flat grey in between two levels dithers into both of them
+        let grey = [0x80; 4 * 4 * 4];
+        let mut levels = convert(&grey, 4, 4, L4, true);
+        levels.sort();
+        levels.dedup();
+        assert!(levels.len() > 1);
+        assert_eq!(convert(&grey, 4, 4, L4, false), [0x88; 8]);
+    }
+}
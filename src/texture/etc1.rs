@@ -0,0 +1,315 @@
+//! ETC1 and ETC1A4 in the PICA's layout.
+//!
+//! Each 4x4 block is a standard ETC1 block, stored as a little endian `u64` (standard ETC1 is big endian).
+//! `ETC1A4` puts a little endian `u64` of 4 bit alphas in front of every block,
+//! with pixel `(x,y)` of the block in bits `4*(x*4+y)..`.
+//! Blocks are grouped into 8x8 tiles, 2x2 blocks each in Z order, and the tiles are ordered like in `tile`,
+//! so the image is upside down.
+//!
+//! Nothing in here touches the GPU, so it also builds on the host.
+
+///https://www.khronos.org/registry/OpenGL/extensions/OES/OES_compressed_ETC1_RGB8_texture.txt
+const MODIFIERS: [[i32; 4]; 8] = [
+    [2, 8, -2, -8],
+    [5, 17, -5, -17],
+    [9, 29, -9, -29],
+    [13, 42, -13, -42],
+    [18, 60, -18, -60],
+    [24, 80, -24, -80],
+    [33, 106, -33, -106],
+    [47, 183, -47, -183],
+];
+
+///Size of an encoded image in bytes
+pub fn encoded_size(width: u32, height: u32, alpha: bool) -> usize {
+    (width * height) as usize / if alpha { 1 } else { 2 }
+}
+
+///`rgba` is linear RGBA8 starting with the top row, `width` and `height` must be multiples of 8.
+///When `alpha` is set this produces `ETC1A4`, otherwise `ETC1`.
+pub fn encode(rgba: &[u8], width: u32, height: u32, alpha: bool) -> Vec<u8> {
+    let mut out = Vec::with_capacity(encoded_size(width, height, alpha));
+    for_each_block(width, height, |bx, by| {
+        let block = read_block(rgba, width, height, bx, by);
+        if alpha {
+            out.extend_from_slice(&encode_alpha(&block).to_le_bytes());
+        }
+        out.extend_from_slice(&encode_block(&block).to_le_bytes());
+    });
+    out
+}
+
+///Inverse of `encode`, producing linear RGBA8 starting with the top row.
+///Without `alpha` every pixel is opaque.
+pub fn decode(data: &[u8], width: u32, height: u32, alpha: bool) -> Vec<u8> {
+    let mut out = vec![0; (width * height) as usize * 4];
+    let mut chunks = data.chunks_exact(8).map(|x| u64::from_le_bytes(x.try_into().unwrap()));
+    for_each_block(width, height, |bx, by| {
+        let alphas = if alpha { chunks.next() } else { None };
+        let colors = decode_block(chunks.next().expect("Not enough data for this size"));
+        for (i, [r, g, b]) in colors.into_iter().enumerate() {
+            let (x, y) = (i as u32 % 4, i as u32 / 4);
+            let a = alphas.map_or(0xFF, |a| {
+                let nibble = (a >> (4 * (x * 4 + y))) & 0xF;
+                (nibble * 0x11) as u8
+            });
+            let index = linear_index(width, height, bx * 4 + x, by * 4 + y);
+            out[index * 4..][..4].copy_from_slice(&[r, g, b, a]);
+        }
+    });
+    out
+}
+
+///Calls `f` with the position of each 4x4 block, in blocks from the bottom left, in the order they are stored
+fn for_each_block(width: u32, height: u32, mut f: impl FnMut(u32, u32)) {
+    assert!(width.is_multiple_of(8) && height.is_multiple_of(8), "Texture sides must be multiples of 8");
+    for ty in 0..height / 8 {
+        for tx in 0..width / 8 {
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                f(tx * 2 + dx, ty * 2 + dy);
+            }
+        }
+    }
+}
+
+///`y` counts up from the bottom of the image
+fn linear_index(width: u32, height: u32, x: u32, y: u32) -> usize {
+    ((height - 1 - y) * width + x) as usize
+}
+
+///Pixels in row major order, rows counting up from the bottom of the image
+fn read_block(rgba: &[u8], width: u32, height: u32, bx: u32, by: u32) -> [[u8; 4]; 16] {
+    std::array::from_fn(|i| {
+        let (x, y) = (bx * 4 + i as u32 % 4, by * 4 + i as u32 / 4);
+        let index = linear_index(width, height, x, y);
+        rgba[index * 4..][..4].try_into().unwrap()
+    })
+}
+
+fn encode_alpha(block: &[[u8; 4]; 16]) -> u64 {
+    let mut out = 0;
+    for (i, pixel) in block.iter().enumerate() {
+        let (x, y) = (i % 4, i / 4);
+        out |= ((pixel[3] as u64 * 15 + 127) / 255) << (4 * (x * 4 + y));
+    }
+    out
+}
+
+///Which half of the block pixel `i` is in
+fn half(i: usize, flip: bool) -> usize {
+    let (x, y) = (i % 4, i / 4);
+    if flip { y / 2 } else { x / 2 }
+}
+
+///Returns the best table, the selector for each of the half's pixels, and the squared error
+fn fit_half(
+    block: &[[u8; 4]; 16],
+    flip: bool,
+    which: usize,
+    base: [i32; 3],
+) -> (u64, [u64; 16], u64) {
+    let mut best = (0, [0; 16], u64::MAX);
+    for (table, modifiers) in MODIFIERS.iter().enumerate() {
+        let mut selectors = [0; 16];
+        let mut error = 0;
+        for (i, pixel) in block.iter().enumerate() {
+            if half(i, flip) != which {
+                continue;
+            }
+            let (selector, e) = modifiers
+                .iter()
+                .map(|m| {
+                    (0..3)
+                        .map(|c| {
+                            let d = (base[c] + m).clamp(0, 255) - pixel[c] as i32;
+                            (d * d) as u64
+                        })
+                        .sum::<u64>()
+                })
+                .enumerate()
+                .min_by_key(|(_, e)| *e)
+                .unwrap();
+            selectors[i] = selector as u64;
+            error += e;
+        }
+        if error < best.2 {
+            best = (table as u64, selectors, error);
+        }
+    }
+    best
+}
+
+fn average(block: &[[u8; 4]; 16], flip: bool, which: usize) -> [f32; 3] {
+    let mut sum = [0.0; 3];
+    for (i, pixel) in block.iter().enumerate() {
+        if half(i, flip) == which {
+            for c in 0..3 {
+                sum[c] += pixel[c] as f32;
+            }
+        }
+    }
+    sum.map(|x| x / 8.0)
+}
+
+///Encodes 16 RGBA8 pixels in row major order into a standard (big endian numbered) ETC1 block.
+///Alpha is ignored.
+pub fn encode_block(block: &[[u8; 4]; 16]) -> u64 {
+    let mut best = (0u64, u64::MAX);
+    for flip in [false, true] {
+        let avg = [average(block, flip, 0), average(block, flip, 1)];
+        //Individual mode, two 4 bit colors
+        let c4 = avg.map(|c| c.map(|x| (x * 15.0 / 255.0).round() as i32));
+        let individual = (
+            c4[0][0] << 28 | c4[1][0] << 24 | c4[0][1] << 20 | c4[1][1] << 16 | c4[0][2] << 12 | c4[1][2] << 8,
+            c4.map(|c| c.map(|x| x * 0x11)),
+        );
+        //Differential mode, a 5 bit color and a 3 bit signed offset
+        let c5 = avg.map(|c| c.map(|x| (x * 31.0 / 255.0).round() as i32));
+        let mut c5b = c5[1];
+        for c in 0..3 {
+            c5b[c] = c5b[c].clamp(c5[0][c] - 4, c5[0][c] + 3).clamp(0, 31);
+        }
+        let d = [0, 1, 2].map(|c| (c5b[c] - c5[0][c]) & 7);
+        let differential = (
+            c5[0][0] << 27 | d[0] << 24 | c5[0][1] << 19 | d[1] << 16 | c5[0][2] << 11 | d[2] << 8 | 2,
+            [c5[0], c5b].map(|c| c.map(|x| (x << 3) | (x >> 2))),
+        );
+        for (high, bases) in [individual, differential] {
+            let (t0, s0, e0) = fit_half(block, flip, 0, bases[0]);
+            let (t1, s1, e1) = fit_half(block, flip, 1, bases[1]);
+            let error = e0 + e1;
+            if error >= best.1 {
+                continue;
+            }
+            let mut bits = ((high as u32 as u64) << 32)
+                | (t0 << 37)
+                | (t1 << 34)
+                | if flip { 1 << 32 } else { 0 };
+            for i in 0..16 {
+                let (x, y) = (i % 4, i / 4);
+                let selector = s0[i] | s1[i];
+                bits |= (selector & 1) << (x * 4 + y);
+                bits |= (selector >> 1) << (16 + x * 4 + y);
+            }
+            best = (bits, error);
+        }
+    }
+    best.0
+}
+
+///Decodes a standard (big endian numbered) ETC1 block into 16 RGB8 pixels in row major order
+pub fn decode_block(bits: u64) -> [[u8; 3]; 16] {
+    let flip = (bits >> 32) & 1 == 1;
+    let differential = (bits >> 33) & 1 == 1;
+    let channel = |shift: u32| ((bits >> shift) & 0xFF) as i32;
+    let bases = if differential {
+        let base0 = [channel(59) & 0x1F, channel(51) & 0x1F, channel(43) & 0x1F];
+        let delta = [56, 48, 40].map(|shift| ((channel(shift) & 7) << 29) >> 29);
+        let base1 = [0, 1, 2].map(|c| (base0[c] + delta[c]) & 0x1F);
+        [base0, base1].map(|c| c.map(|x| (x << 3) | (x >> 2)))
+    } else {
+        [[60, 52, 44], [56, 48, 40]].map(|c| c.map(|shift| (channel(shift) & 0xF) * 0x11))
+    };
+    let tables = [(bits >> 37) & 7, (bits >> 34) & 7].map(|t| MODIFIERS[t as usize]);
+    std::array::from_fn(|i| {
+        let (x, y) = (i % 4, i / 4);
+        let which = half(i, flip);
+        let selector = ((bits >> (x * 4 + y)) & 1) | (((bits >> (16 + x * 4 + y)) & 1) << 1);
+        let m = tables[which][selector as usize];
+        bases[which].map(|c| (c + m).clamp(0, 255) as u8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: [u8; 3], b: [u8; 3], tolerance: i32) -> bool {
+        (0..3).all(|c| (a[c] as i32 - b[c] as i32).abs() <= tolerance)
+    }
+
+    ///Linear RGBA8, `color(x, y)` with `y == 0` the top row
+    fn image(width: u32, height: u32, color: impl Fn(u32, u32) -> [u8; 4]) -> Vec<u8> {
+        (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).flat_map(|(x, y)| color(x, y)).collect()
+    }
+
+    #[test]
+    fn decode_individual_block() {
+        //Red 0x8 and 0x4, split left and right, table 0, every selector +2
+        let bits = (0x8 << 60) | (0x4 << 56);
+        let pixels = decode_block(bits);
+        for (i, pixel) in pixels.into_iter().enumerate() {
+            let expected = if i % 4 < 2 { [0x88 + 2, 2, 2] } else { [0x44 + 2, 2, 2] };
+            assert_eq!(pixel, expected, "pixel {i}");
+        }
+    }
+
+    #[test]
+    fn decode_differential_block() {
+        //Red 16 and 16-1 in 5 bits, split top and bottom, table 0, every selector -2
+        let bits = (16 << 59) | (7 << 56) | (1 << 33) | (1 << 32) | (0xFFFF << 16);
+        let pixels = decode_block(bits);
+        for (i, pixel) in pixels.into_iter().enumerate() {
+            let expected = if i / 4 < 2 { [132 - 2, 0, 0] } else { [123 - 2, 0, 0] };
+            assert_eq!(pixel, expected, "pixel {i}");
+        }
+    }
+
+    #[test]
+    fn encode_solid_block() {
+        for color in [[0, 0, 0], [255, 255, 255], [200, 100, 50], [17, 240, 128]] {
+            let block = [[color[0], color[1], color[2], 0xFF]; 16];
+            for pixel in decode_block(encode_block(&block)) {
+                assert!(close(pixel, color, 4), "{pixel:?} for {color:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let (width, height) = (16, 16);
+        //Solid 4x4 blocks, so the only error is from the shared modifier not fitting every channel
+        let rgba = image(width, height, |x, y| {
+            let (bx, by) = (x / 4, y / 4);
+            [(bx * 60) as u8, (by * 60) as u8, ((bx + by) * 30) as u8, ((bx ^ by) * 0x55) as u8]
+        });
+        for alpha in [false, true] {
+            let encoded = encode(&rgba, width, height, alpha);
+            assert_eq!(encoded.len(), encoded_size(width, height, alpha));
+            let decoded = decode(&encoded, width, height, alpha);
+            for (i, (a, b)) in rgba.chunks(4).zip(decoded.chunks(4)).enumerate() {
+                assert!(close([a[0], a[1], a[2]], [b[0], b[1], b[2]], 8), "pixel {i}: {a:?} {b:?}");
+                //The alphas are multiples of 0x11, so they survive being cut to 4 bits
+                assert_eq!(b[3], if alpha { a[3] } else { 0xFF }, "pixel {i}");
+            }
+        }
+    }
+
+    #[test]
+    fn blocks_in_z_order_from_the_bottom() {
+        let quadrant = [[255, 0, 0], [0, 255, 0], [0, 0, 255], [255, 255, 255]];
+        //Top left, top right, bottom left, bottom right
+        let rgba = image(8, 8, |x, y| {
+            let [r, g, b] = quadrant[(y / 4 * 2 + x / 4) as usize];
+            [r, g, b, 0xFF]
+        });
+        let encoded = encode(&rgba, 8, 8, false);
+        let blocks: Vec<u64> = encoded.chunks(8).map(|x| u64::from_le_bytes(x.try_into().unwrap())).collect();
+        //Bottom left, bottom right, top left, top right
+        for (block, expected) in blocks.into_iter().zip([2, 3, 0, 1]) {
+            assert!(close(decode_block(block)[0], quadrant[expected], 4));
+        }
+    }
+
+    #[test]
+    fn alpha_nibbles_column_major_from_the_bottom() {
+        let rgba = image(8, 8, |x, y| match (x, y) {
+            (1, 7) => [0, 0, 0, 0xFF],
+            (0, 6) => [0, 0, 0, 0x88],
+            _ => [0, 0, 0, 0],
+        });
+        let encoded = encode(&rgba, 8, 8, true);
+        let alphas = u64::from_le_bytes(encoded[..8].try_into().unwrap());
+        assert_eq!(alphas, (0xF << (4 * 4)) | (0x8 << 4));
+    }
+}
@@ -0,0 +1,86 @@
+//! Box filtered mip chains, generated on the CPU from linear (untiled) pixel data.
+
+use super::{Format, pixel};
+
+///Halves a linear image, each output pixel is the average of a 2x2 block.
+///Panics on `ETC1` and `ETC1A4`.
+pub fn downscale(format: Format, src: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let (w, h) = (width / 2, height / 2);
+    let mut dst = vec![0; (w * h) as usize * format.bitsize() / 8];
+    for y in 0..h {
+        for x in 0..w {
+            let mut sum = [0u32; 4];
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                let p = pixel::read(format, src, ((y * 2 + dy) * width + x * 2 + dx) as usize);
+                for c in 0..4 {
+                    sum[c] += p[c] as u32;
+                }
+            }
+            pixel::write(format, &mut dst, (y * w + x) as usize, sum.map(|s| ((s + 2) / 4) as u8));
+        }
+    }
+    dst
+}
+
+///Returns levels `1..=max_mip`, level 0 being `data` itself.
+///Panics on `ETC1` and `ETC1A4`.
+pub fn generate(format: Format, data: &[u8], width: u32, height: u32, max_mip: u8) -> Vec<Vec<u8>> {
+    let mut levels: Vec<Vec<u8>> = Vec::with_capacity(max_mip as usize);
+    let (mut w, mut h) = (width, height);
+    for _ in 0..max_mip {
+        let next = downscale(format, levels.last().map_or(data, |x| x), w, h);
+        levels.push(next);
+        w /= 2;
+        h /= 2;
+    }
+    levels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::{Mode, TextureDescriptor};
+
+    #[test]
+    fn rgba8() {
+        //Stored as ABGR, but every byte is averaged on its own either way
+        let src = [0, 0, 0, 0, 4, 8, 12, 16, 8, 16, 24, 32, 255, 255, 255, 255];
+        assert_eq!(downscale(Format::RGBA8, &src, 2, 2), [67, 70, 73, 76]);
+    }
+
+    #[test]
+    fn rgb565() {
+        //Two white and two black pixels average to 128 grey
+        let src = [0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF];
+        assert_eq!(downscale(Format::RGB565, &src, 2, 2), [0x10, 0x84]);
+    }
+
+    #[test]
+    fn l4() {
+        //4x2, the left half is two black and two white pixels, the right half is all white
+        let src = [0x00, 0xFF, 0xFF, 0xFF];
+        assert_eq!(downscale(Format::L4, &src, 4, 2), [0xF8]);
+    }
+
+    #[test]
+    fn generate_sizes() {
+        let descriptor = TextureDescriptor {
+            width: 64,
+            height: 32,
+            max_mip: 3,
+            format: Format::RGBA8,
+            mode: Mode::Ordinary,
+            vram: false,
+        };
+        let data = [0x40; 64 * 32 * 4];
+        let levels = generate(Format::RGBA8, &data, 64, 32, descriptor.max_mip);
+        assert_eq!(levels.len(), 3);
+        for (i, level) in levels.iter().enumerate() {
+            let mip = i as u8 + 1;
+            let pixels = descriptor.mip_width(mip) as usize * descriptor.mip_height(mip) as usize;
+            assert_eq!(level.len(), pixels * 4);
+            assert_eq!(level.len(), descriptor.bytesize_mip(mip));
+            assert!(level.iter().all(|&b| b == 0x40));
+        }
+    }
+}
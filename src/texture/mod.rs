@@ -0,0 +1,407 @@
+use crate::queue::TransferFormat;
+#[cfg(target_os = "horizon")]
+use crate::{
+    buffer::Buffer,
+    queue::{Queue, ScaleDownFilter, TransferFlags, gx_display_transfer},
+    renderbuffer::dim,
+};
+
+pub mod convert;
+pub mod etc1;
+pub mod mip;
+pub mod pixel;
+pub mod t3x;
+pub mod tile;
+
+#[cfg(target_os = "horizon")]
+pub struct Texture {
+    data: Buffer,
+    descriptor: TextureDescriptor
+}
+
+#[derive(Clone, Copy)]
+pub struct TextureDescriptor {
+    pub width: u16,
+    pub height: u16,
+    pub max_mip: u8,
+    pub format: Format,
+    pub mode: Mode,
+    pub vram: bool
+}
+
+impl TextureDescriptor {
+    pub fn bytesize_mip0(&self) -> usize {
+        // >>3 = /8
+        (self.format.bitsize() * self.width as usize * self.height as usize) >> 3
+    }
+    pub fn bytesize_mip0_to_mipn(bytesize: usize,mip_level: usize) -> usize {
+        bytesize >> (2*mip_level)
+    }
+    pub fn bytesize_mip(&self, mip_level: u8) -> usize {
+        Self::bytesize_mip0_to_mipn(self.bytesize_mip0(), mip_level as usize)
+    }
+    ///Offset of a mip level from the start of the texture data
+    pub fn mip_offset(&self, mip_level: u8) -> usize {
+        let size = self.bytesize_mip0();
+        (size - Self::bytesize_mip0_to_mipn(size, mip_level as usize)) * 4 / 3
+    }
+    ///Size of one face, including all of its mip levels
+    pub fn bytesize_total(&self) -> usize {
+        self.mip_offset(self.max_mip + 1)
+    }
+    ///Offset of a face from the start of the texture data, faces are stored one after another
+    pub fn face_offset(&self, face: Face) -> usize {
+        face.index() * self.bytesize_total()
+    }
+    pub fn mip_width(&self, mip_level: u8) -> u16 {
+        self.width >> mip_level
+    }
+    pub fn mip_height(&self, mip_level: u8) -> u16 {
+        self.height >> mip_level
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    ModeNotSupported,
+    IncorrectSideLength,
+    ///Every mip level must be at least 8x8
+    IncorrectMipCount,
+    WrongDataSize,
+    FormatNotSupported,
+    ///Cube maps only have the six cube faces, every other mode only has `Face::D2`
+    FaceNotInTexture,
+    Queue(crate::queue::Error),
+    T3x(t3x::Error),
+}
+
+#[cfg(target_os = "horizon")]
+impl Texture {
+    pub fn new(descriptor: TextureDescriptor) -> Result<Self,Error> {
+        if descriptor.mode == Mode::Disabled {
+            return Err(Error::ModeNotSupported);
+        }
+        if descriptor.mode.is_shadow() && descriptor.format != Format::RGBA8 {
+            return Err(Error::FormatNotSupported);
+        }
+        if !Texture::valid_size(descriptor.width) || !Texture::valid_size(descriptor.height) {
+            return Err(Error::IncorrectSideLength);
+        }
+        if (descriptor.width.min(descriptor.height) >> descriptor.max_mip) < 8 {
+            return Err(Error::IncorrectMipCount);
+        }
+        let size = descriptor.bytesize_total() * descriptor.mode.faces();
+        let data = Buffer::new(size,descriptor.vram);
+        Ok(Texture {
+            data,
+            descriptor
+        })
+    }
+    ///Creates a texture from a `.t3x` file and uploads its data, returning the sub textures with it
+    pub fn from_t3x(
+        queue: &Queue,
+        file: &[u8],
+        vram: bool,
+    ) -> Result<(Self, Vec<t3x::SubTexture>), Error> {
+        let t3x = t3x::T3x::parse_file(file, vram).map_err(Error::T3x)?;
+        let mut texture = Texture::new(t3x.descriptor)?;
+        texture.write_with(queue, 0, t3x.data.len(), |dst| dst.copy_from_slice(&t3x.data))?;
+        Ok((texture, t3x.sub_textures))
+    }
+    pub fn valid_size(size: u16) -> bool {
+        size >= 8 && size <= 1024 && size.is_power_of_two()
+    }
+    pub fn slice(&self, rect: Rect, mip_level: u8, face: Face) -> TextureSlice {
+        TextureSlice { texture: self, rect, mip_level, face }
+    }
+    pub fn descriptor(&self) -> &TextureDescriptor {
+        &self.descriptor
+    }
+    pub(crate) fn phys_addr(&self) -> u32 {
+        self.data.phys_addr()
+    }
+    ///The faces this texture has, in the order they are stored
+    pub fn faces(&self) -> &'static [Face] {
+        self.descriptor.mode.face_list()
+    }
+    ///Uploads mip level 0, see `upload_level`
+    pub fn upload(&mut self, queue: &Queue, data: &[u8]) -> Result<(), Error> {
+        self.upload_level(queue, 0, data)
+    }
+    ///Uploads to `Face::D2`, see `upload_face`
+    pub fn upload_level(&mut self, queue: &Queue, mip_level: u8, data: &[u8]) -> Result<(), Error> {
+        self.upload_face(queue, Face::D2, mip_level, data)
+    }
+    ///`data` is linear, starting with the top row, and already in the texture's `Format`.
+    ///`ETC1` and `ETC1A4` data is expected to already be in the PICA's block layout, and is copied as is.
+    pub fn upload_face(
+        &mut self,
+        queue: &Queue,
+        face: Face,
+        mip_level: u8,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        let format = self.descriptor.format;
+        if format.is_compressed() {
+            return self.upload_raw(queue, face, mip_level, data);
+        }
+        let slice = self.level(face, mip_level, data.len())?;
+        let (offset, size) = (slice.offset(), slice.size());
+        let (width, height) = (slice.width() as u32, slice.height() as u32);
+        self.write_with(queue, offset, size, |dst| {
+            tile::tile(data, dst, width, height, format.bitsize())
+        })
+    }
+    ///Copies `data` as is, it must already be tiled like the PICA stores it, for example by `convert::bake` or `tile::tile`
+    pub fn upload_raw(
+        &mut self,
+        queue: &Queue,
+        face: Face,
+        mip_level: u8,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        let slice = self.level(face, mip_level, data.len())?;
+        let (offset, size) = (slice.offset(), slice.size());
+        self.write_with(queue, offset, size, |dst| dst.copy_from_slice(data))
+    }
+    ///The whole of one mip level of `face`, checking that `len` bytes of data fill it exactly
+    fn level(&self, face: Face, mip_level: u8, len: usize) -> Result<TextureSlice<'_>, Error> {
+        if !self.faces().contains(&face) {
+            return Err(Error::FaceNotInTexture);
+        }
+        let slice = self.slice(Rect::default(), mip_level, face);
+        if mip_level > self.descriptor.max_mip || len != slice.size() {
+            return Err(Error::WrongDataSize);
+        }
+        Ok(slice)
+    }
+    ///Uploads `data` as mip level 0 of `face`, then fills every other level with `mip::generate`
+    pub fn upload_with_mipmaps(&mut self, queue: &Queue, face: Face, data: &[u8]) -> Result<(), Error> {
+        let TextureDescriptor { width, height, max_mip, format, .. } = self.descriptor;
+        if format.is_compressed() {
+            return Err(Error::FormatNotSupported);
+        }
+        self.upload_face(queue, face, 0, data)?;
+        for (level, mip) in mip::generate(format, data, width as u32, height as u32, max_mip)
+            .iter()
+            .enumerate()
+        {
+            self.upload_face(queue, face, level as u8 + 1, mip)?;
+        }
+        Ok(())
+    }
+    ///Fills every mip level past 0 by downscaling the previous one with the transfer engine.
+    ///Only works for the formats the transfer engine can read and write, and waits for each level to finish.
+    pub fn generate_mipmaps_gpu(&mut self, queue: &Queue) -> Result<(), Error> {
+        let format = self
+            .descriptor
+            .format
+            .transfer_format()
+            .ok_or(Error::FormatNotSupported)?;
+        let flags = TransferFlags {
+            flip_vert: false,
+            tiled_out: false,
+            output_width_less_than_input_width: false,
+            texture_copy: false,
+            tiled_to_tiled: true,
+            input_color_format: format,
+            output_color_format: format,
+            block_tiling_mode: false,
+            scale_down_filter: ScaleDownFilter::DownXY,
+        };
+        let base = self.data.addr();
+        for &face in self.faces() {
+            for level in 1..=self.descriptor.max_mip {
+                let src = self.slice(Rect::default(), level - 1, face);
+                let dst = self.slice(Rect::default(), level, face);
+                unsafe {
+                    queue
+                        .submit_command(gx_display_transfer(
+                            base.byte_add(src.offset()),
+                            dim(src.width() as u32, src.height() as u32),
+                            base.byte_add(dst.offset()).cast_mut(),
+                            dim(dst.width() as u32, dst.height() as u32),
+                            flags.into(),
+                        ))
+                        .map_err(Error::Queue)?;
+                }
+                ctru::services::gspgpu::wait_for_event(ctru::services::gspgpu::Event::PPF, false);
+            }
+        }
+        Ok(())
+    }
+    ///Lets `f` fill `size` bytes at `offset` of the texture data.
+    ///VRAM textures are filled through a linear staging buffer and a DMA, which this waits for.
+    pub(crate) fn write_with(
+        &mut self,
+        queue: &Queue,
+        offset: usize,
+        size: usize,
+        f: impl FnOnce(&mut [u8]),
+    ) -> Result<(), Error> {
+        let mut dst = self.data.slice(offset..offset + size);
+        if let Some(mapped) = dst.map_mut() {
+            f(mapped);
+            unsafe {
+                ctru_sys::GSPGPU_FlushDataCache(mapped.as_ptr().cast(), size as u32);
+            }
+            return Ok(());
+        }
+        let mut staging = Buffer::new(size, false);
+        let mut src = staging.slice(..);
+        f(src.map_mut().expect("Staging buffer is in linear memory"));
+        queue.copy_buffer(src, dst, true).map_err(Error::Queue)?;
+        ctru::services::gspgpu::wait_for_event(ctru::services::gspgpu::Event::DMA, false);
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+#[cfg(target_os = "horizon")]
+pub struct TextureSlice<'a> {
+    texture: &'a Texture,
+    rect: Rect,
+    mip_level: u8,
+    face: Face
+}
+
+#[cfg(target_os = "horizon")]
+impl<'a> TextureSlice<'a> {
+    pub fn width(&self) -> u16 {
+        self.texture.descriptor.mip_width(self.mip_level)
+    }
+    pub fn height(&self) -> u16 {
+        self.texture.descriptor.mip_height(self.mip_level)
+    }
+    ///Offset of the slice's face and mip level from the start of the texture data.
+    ///Note: as the level is tiled, `rect` does not narrow this down.
+    pub fn offset(&self) -> usize {
+        self.texture.descriptor.face_offset(self.face)
+            + self.texture.descriptor.mip_offset(self.mip_level)
+    }
+    ///Size of the whole mip level
+    pub fn size(&self) -> usize {
+        self.texture.descriptor.bytesize_mip(self.mip_level)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum Format {
+    RGBA8,
+    RGB8,
+    RGBA5551,
+    RGB565,
+    RGBA4,
+    LA8,
+    HILO8,
+    L8,
+    A8,
+    LA4,
+    L4,
+    A4,
+    ETC1,
+    ETC1A4,
+}
+
+impl Format {
+    ///`ETC1` and `ETC1A4` are stored as 4x4 blocks instead of as tiled pixels
+    pub fn is_compressed(self) -> bool {
+        matches!(self, Format::ETC1 | Format::ETC1A4)
+    }
+    ///The formats that the transfer engine can also read and write
+    pub fn transfer_format(self) -> Option<TransferFormat> {
+        match self {
+            Format::RGBA8 => Some(TransferFormat::RGBA8),
+            Format::RGB8 => Some(TransferFormat::RGB8),
+            Format::RGBA5551 => Some(TransferFormat::RGB5A1),
+            Format::RGB565 => Some(TransferFormat::RGB565),
+            Format::RGBA4 => Some(TransferFormat::RGBA4),
+            _ => None,
+        }
+    }
+    pub fn bitsize(self) -> usize {
+        use Format::*;
+        match self {
+            RGBA8 => 32,
+            RGB8 => 24,
+            RGBA5551 | RGB565 | RGBA4 | LA8 | HILO8 => 16,
+            L8 | A8 | LA4 | ETC1A4 => 8,
+            L4 | A4 | ETC1 => 4,
+        }
+    }
+}
+
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+#[repr(u8)]
+pub enum Mode {
+    ///2D texture
+    Ordinary,
+    ///Cube Map
+    CubeMap,
+    ///2D Shadow Texture
+    Shadow2d,
+    ///Projection
+    Projection,
+    ///Shadow Cube Map
+    ShadowCube,
+    ///Disabled
+    Disabled
+}
+
+impl Mode {
+    pub fn is_shadow(self) -> bool {
+        matches!(self, Mode::Shadow2d | Mode::ShadowCube)
+    }
+    pub fn is_cube(self) -> bool {
+        matches!(self, Mode::CubeMap | Mode::ShadowCube)
+    }
+    pub fn faces(self) -> usize {
+        self.face_list().len()
+    }
+    pub fn face_list(self) -> &'static [Face] {
+        if self.is_cube() {
+            &Face::CUBE
+        } else {
+            &[Face::D2]
+        }
+    }
+}
+
+#[derive(Copy,Clone,Eq,PartialEq,Debug)]
+#[repr(u8)]
+pub enum Face {
+    ///The only face of anything that isn't a cube map
+    D2,
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ
+}
+
+impl Face {
+    ///In the order they are stored, and given to `GPUREG_TEXUNIT0_ADDR1..=6`
+    pub const CUBE: [Face; 6] = [
+        Face::PositiveX,
+        Face::NegativeX,
+        Face::PositiveY,
+        Face::NegativeY,
+        Face::PositiveZ,
+        Face::NegativeZ,
+    ];
+    pub fn index(self) -> usize {
+        match self {
+            Face::D2 => 0,
+            _ => self as usize - 1,
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! Reading and writing single pixels of the uncompressed `Format`s as RGBA8.
+//!
+//! Pixels are stored little endian with the first channel in the highest bits,
+//! so `RGBA8` is `[a,b,g,r]` in memory and `RGB565` is `r << 11 | g << 5 | b`.
+//! Luminance formats read back as grey, alpha only formats as black.
+
+use super::Format;
+
+///Panics on `ETC1` and `ETC1A4`, which aren't made of pixels.
+pub fn read(format: Format, data: &[u8], index: usize) -> [u8; 4] {
+    use Format::*;
+    match format {
+        RGBA8 => {
+            let [a, b, g, r] = data[index * 4..][..4].try_into().unwrap();
+            [r, g, b, a]
+        }
+        RGB8 => {
+            let [b, g, r] = data[index * 3..][..3].try_into().unwrap();
+            [r, g, b, 0xFF]
+        }
+        RGBA5551 => {
+            let v = read_u16(data, index);
+            [expand(v >> 11, 5), expand(v >> 6, 5), expand(v >> 1, 5), expand(v, 1)]
+        }
+        RGB565 => {
+            let v = read_u16(data, index);
+            [expand(v >> 11, 5), expand(v >> 5, 6), expand(v, 5), 0xFF]
+        }
+        RGBA4 => {
+            let v = read_u16(data, index);
+            [expand(v >> 12, 4), expand(v >> 8, 4), expand(v >> 4, 4), expand(v, 4)]
+        }
+        LA8 => {
+            let [a, l] = data[index * 2..][..2].try_into().unwrap();
+            [l, l, l, a]
+        }
+        HILO8 => {
+            let [lo, hi] = data[index * 2..][..2].try_into().unwrap();
+            [hi, lo, 0, 0xFF]
+        }
+        L8 => grey(data[index], 0xFF),
+        A8 => [0, 0, 0, data[index]],
+        LA4 => {
+            let v = data[index] as u32;
+            grey(expand(v >> 4, 4), expand(v, 4))
+        }
+        L4 => grey(expand(read_nibble(data, index), 4), 0xFF),
+        A4 => [0, 0, 0, expand(read_nibble(data, index), 4)],
+        ETC1 | ETC1A4 => panic!("Compressed formats have no pixels"),
+    }
+}
+
+///Panics on `ETC1` and `ETC1A4`, which aren't made of pixels.
+pub fn write(format: Format, data: &mut [u8], index: usize, rgba: [u8; 4]) {
+    use Format::*;
+    let [r, g, b, a] = rgba;
+    match format {
+        RGBA8 => data[index * 4..][..4].copy_from_slice(&[a, b, g, r]),
+        RGB8 => data[index * 3..][..3].copy_from_slice(&[b, g, r]),
+        RGBA5551 => write_u16(
+            data,
+            index,
+            (quantize(r, 5) << 11) | (quantize(g, 5) << 6) | (quantize(b, 5) << 1) | quantize(a, 1),
+        ),
+        RGB565 => write_u16(
+            data,
+            index,
+            (quantize(r, 5) << 11) | (quantize(g, 6) << 5) | quantize(b, 5),
+        ),
+        RGBA4 => write_u16(
+            data,
+            index,
+            (quantize(r, 4) << 12) | (quantize(g, 4) << 8) | (quantize(b, 4) << 4) | quantize(a, 4),
+        ),
+        LA8 => data[index * 2..][..2].copy_from_slice(&[a, luminance(rgba)]),
+        HILO8 => data[index * 2..][..2].copy_from_slice(&[g, r]),
+        L8 => data[index] = luminance(rgba),
+        A8 => data[index] = a,
+        LA4 => data[index] = ((quantize(luminance(rgba), 4) << 4) | quantize(a, 4)) as u8,
+        L4 => write_nibble(data, index, quantize(luminance(rgba), 4)),
+        A4 => write_nibble(data, index, quantize(a, 4)),
+        ETC1 | ETC1A4 => panic!("Compressed formats have no pixels"),
+    }
+}
+
+///Rec. 601 luma
+pub fn luminance([r, g, b, _]: [u8; 4]) -> u8 {
+    ((r as u32 * 77 + g as u32 * 150 + b as u32 * 29 + 128) >> 8) as u8
+}
+
+///Rounds an 8 bit channel down to `bits`
+pub fn quantize(v: u8, bits: u32) -> u32 {
+    (v as u32 * ((1 << bits) - 1) + 127) / 255
+}
+
+///Scales the low `bits` of `v` back up to 8 bits
+pub fn expand(v: u32, bits: u32) -> u8 {
+    let max = (1 << bits) - 1;
+    ((v & max) * 255 / max) as u8
+}
+
+fn grey(l: u8, a: u8) -> [u8; 4] {
+    [l, l, l, a]
+}
+
+fn read_u16(data: &[u8], index: usize) -> u32 {
+    u16::from_le_bytes([data[index * 2], data[index * 2 + 1]]) as u32
+}
+
+fn write_u16(data: &mut [u8], index: usize, v: u32) {
+    data[index * 2..][..2].copy_from_slice(&(v as u16).to_le_bytes());
+}
+
+fn read_nibble(data: &[u8], index: usize) -> u32 {
+    ((data[index / 2] >> ((index & 1) * 4)) & 0xF) as u32
+}
+
+fn write_nibble(data: &mut [u8], index: usize, v: u32) {
+    let shift = (index & 1) * 4;
+    let byte = &mut data[index / 2];
+    *byte = (*byte & !(0xF << shift)) | (((v & 0xF) as u8) << shift);
+}
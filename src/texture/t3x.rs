@@ -0,0 +1,335 @@
+//! `.t3x` files, as written by `tex3ds`.
+//!
+//! The file is a 5 byte header, a table of sub textures (for atlases),
+//! then the texture data behind a compression header.
+//! The data is already tiled and contains every mip level, and every face for cube maps,
+//! so it can be copied into a `Texture` as is.
+//! https://github.com/devkitPro/tex3ds
+
+use super::{Format, Mode, TextureDescriptor};
+
+#[derive(Debug)]
+pub enum Error {
+    UnexpectedEof,
+    BadFormat(u8),
+    BadCompression(u8),
+    ///A back reference points before the start of the output
+    BadReference,
+    ///The decompressed size does not match the size of the texture
+    WrongDataSize,
+}
+
+use Error::UnexpectedEof as EOF;
+
+#[derive(Clone, Copy, Debug)]
+pub struct SubTexture {
+    ///In pixels
+    pub width: u16,
+    ///In pixels
+    pub height: u16,
+    ///Texture coordinates of the edges
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+pub struct T3x {
+    pub descriptor: TextureDescriptor,
+    pub sub_textures: Vec<SubTexture>,
+    ///Decompressed texture data, every face one after another
+    pub data: Vec<u8>,
+}
+
+impl TryFrom<u8> for Format {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        use Format::*;
+        const ALL: [Format; 14] = [
+            RGBA8, RGB8, RGBA5551, RGB565, RGBA4, LA8, HILO8, L8, A8, LA4, L4, A4, ETC1, ETC1A4,
+        ];
+        ALL.get(value as usize).copied().ok_or(Error::BadFormat(value))
+    }
+}
+
+impl T3x {
+    ///`vram` is only copied into the descriptor
+    pub fn parse_file(file: &[u8], vram: bool) -> Result<T3x, Error> {
+        let header = file.get(..5).ok_or(EOF)?;
+        let num_sub_textures = u16::from_le_bytes([header[0], header[1]]) as usize;
+        let mode = if header[2] & 0x40 != 0 { Mode::CubeMap } else { Mode::Ordinary };
+        let descriptor = TextureDescriptor {
+            width: 8 << (header[2] & 7),
+            height: 8 << ((header[2] >> 3) & 7),
+            max_mip: header[4],
+            format: Format::try_from(header[3])?,
+            mode,
+            vram,
+        };
+        let table = file.get(5..5 + num_sub_textures * 12).ok_or(EOF)?;
+        let sub_textures = table
+            .chunks_exact(12)
+            .map(|entry| {
+                let field = |i: usize| u16::from_le_bytes([entry[i * 2], entry[i * 2 + 1]]);
+                SubTexture {
+                    width: field(0),
+                    height: field(1),
+                    left: field(2) as f32 / 1024.0,
+                    top: field(3) as f32 / 1024.0,
+                    right: field(4) as f32 / 1024.0,
+                    bottom: field(5) as f32 / 1024.0,
+                }
+            })
+            .collect();
+        let compressed = &file[5 + table.len()..];
+        //Checked before decompressing, so a bad size can't have it allocate arbitrarily much
+        if decompressed_size(compressed)? != descriptor.bytesize_total() * mode.faces() {
+            return Err(Error::WrongDataSize);
+        }
+        let data = decompress(compressed)?;
+        Ok(T3x {
+            descriptor,
+            sub_textures,
+            data,
+        })
+    }
+}
+
+///The compression kind, the decompressed size and the compressed data
+fn header(data: &[u8]) -> Result<(u8, usize, &[u8]), Error> {
+    let header = data.get(..4).ok_or(EOF)?;
+    let size = u32::from_le_bytes([header[1], header[2], header[3], 0]) as usize;
+    let src = &data[4..];
+    //Sizes that don't fit in 24 bits are written after a size of 0
+    if size == 0 {
+        let extended = src.get(..4).ok_or(EOF)?;
+        return Ok((header[0], u32::from_le_bytes(extended.try_into().unwrap()) as usize, &src[4..]));
+    }
+    Ok((header[0], size, src))
+}
+
+///Size of the data `decompress` returns, as written in the compression header
+pub fn decompressed_size(data: &[u8]) -> Result<usize, Error> {
+    header(data).map(|(_, size, _)| size)
+}
+
+///Decompresses data behind a compression header, as used by `tex3ds` and the GBA/DS BIOS.
+///Supports no compression (0x00), LZ10 (0x10), LZ11 (0x11), Huffman (0x24, 0x28) and RLE (0x30).
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let (kind, size, src) = header(data)?;
+    //The size comes from the file, so only what the input can back is reserved up front
+    let mut out = Vec::with_capacity(size.min(src.len()));
+    match kind {
+        0x00 => out.extend_from_slice(src.get(..size).ok_or(EOF)?),
+        0x10 | 0x11 => lz(src, size, kind == 0x11, &mut out)?,
+        0x24 | 0x28 => huffman(src, size, (kind & 0xF) as u32, &mut out)?,
+        0x30 => rle(src, size, &mut out)?,
+        _ => return Err(Error::BadCompression(kind)),
+    }
+    Ok(out)
+}
+
+///Byte reader for the decompressors
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Result<u8, Error> {
+        let (&first, rest) = self.0.split_first().ok_or(EOF)?;
+        self.0 = rest;
+        Ok(first)
+    }
+}
+
+fn lz(src: &[u8], size: usize, lz11: bool, out: &mut Vec<u8>) -> Result<(), Error> {
+    let mut src = Reader(src);
+    while out.len() < size {
+        let flags = src.byte()?;
+        for bit in (0..8).rev() {
+            if out.len() >= size {
+                break;
+            }
+            if flags & (1 << bit) == 0 {
+                out.push(src.byte()?);
+                continue;
+            }
+            let b0 = src.byte()? as usize;
+            let (len, b1) = if !lz11 {
+                ((b0 >> 4) + 3, b0)
+            } else {
+                match b0 >> 4 {
+                    0 => {
+                        let b1 = src.byte()? as usize;
+                        (((b0 & 0xF) << 4 | b1 >> 4) + 0x11, b1)
+                    }
+                    1 => {
+                        let b1 = src.byte()? as usize;
+                        let b2 = src.byte()? as usize;
+                        (((b0 & 0xF) << 12 | b1 << 4 | b2 >> 4) + 0x111, b2)
+                    }
+                    _ => ((b0 >> 4) + 1, b0),
+                }
+            };
+            let disp = ((b1 & 0xF) << 8 | src.byte()? as usize) + 1;
+            let start = out.len().checked_sub(disp).ok_or(Error::BadReference)?;
+            for i in 0..len.min(size - out.len()) {
+                out.push(out[start + i]);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn rle(src: &[u8], size: usize, out: &mut Vec<u8>) -> Result<(), Error> {
+    let mut src = Reader(src);
+    while out.len() < size {
+        let flag = src.byte()?;
+        if flag & 0x80 != 0 {
+            let byte = src.byte()?;
+            let len = ((flag & 0x7F) as usize + 3).min(size - out.len());
+            out.extend(std::iter::repeat_n(byte, len));
+        } else {
+            for _ in 0..(flag as usize + 1).min(size - out.len()) {
+                out.push(src.byte()?);
+            }
+        }
+    }
+    Ok(())
+}
+
+///The tree starts with its size, nodes are a 6 bit offset to their pair of children,
+///with bit 7 set if the left child is data and bit 6 if the right one is.
+///The bitstream is little endian `u32`s, read from the most significant bit.
+fn huffman(src: &[u8], size: usize, bits: u32, out: &mut Vec<u8>) -> Result<(), Error> {
+    let tree_size = (*src.first().ok_or(EOF)? as usize + 1) * 2;
+    let tree = src.get(..tree_size).ok_or(EOF)?;
+    let stream = &src[tree_size..];
+    let mut words = stream
+        .chunks_exact(4)
+        .map(|word| u32::from_le_bytes(word.try_into().unwrap()));
+    let mut node = 1;
+    let mut pending: Option<u8> = None;
+    while out.len() < size {
+        let word = words.next().ok_or(EOF)?;
+        for bit in (0..32).rev() {
+            let value = *tree.get(node).ok_or(EOF)?;
+            let right = (word >> bit) & 1;
+            let child = (node & !1) + (value as usize & 0x3F) * 2 + 2 + right as usize;
+            let is_data = value & (0x80 >> right) != 0;
+            if !is_data {
+                node = child;
+                continue;
+            }
+            let data = *tree.get(child).ok_or(EOF)?;
+            node = 1;
+            if bits == 8 {
+                out.push(data);
+            } else if let Some(low) = pending.take() {
+                out.push(low | (data & 0xF) << 4);
+            } else {
+                pending = Some(data & 0xF);
+            }
+            if out.len() >= size {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uncompressed() {
+        assert_eq!(decompress(&[0x00, 3, 0, 0, 1, 2, 3, 4]).unwrap(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn extended_size() {
+        assert_eq!(decompress(&[0x00, 0, 0, 0, 2, 0, 0, 0, 9, 8]).unwrap(), [9, 8]);
+        assert_eq!(decompressed_size(&[0x00, 0, 0, 0, 0, 0, 0, 1]).unwrap(), 1 << 24);
+    }
+
+    #[test]
+    fn lz10() {
+        //Three literals, then 6 bytes from 3 back
+        let data = [0x10, 9, 0, 0, 0b0001_0000, b'a', b'b', b'c', 0x30, 0x02];
+        assert_eq!(decompress(&data).unwrap(), b"abcabcabc");
+    }
+
+    #[test]
+    fn lz11() {
+        let short = [0x11, 9, 0, 0, 0b0001_0000, b'a', b'b', b'c', 0x50, 0x02];
+        assert_eq!(decompress(&short).unwrap(), b"abcabcabc");
+        //19 bytes, in the 2 byte length form
+        let medium = [0x11, 20, 0, 0, 0b0100_0000, b'a', 0x00, 0x20, 0x00];
+        assert_eq!(decompress(&medium).unwrap(), [b'a'; 20]);
+        //299 bytes, in the 3 byte length form
+        let long = [0x11, 0x2C, 0x01, 0, 0b0100_0000, b'z', 0x10, 0x01, 0xA0, 0x00];
+        assert_eq!(decompress(&long).unwrap(), [b'z'; 300]);
+    }
+
+    #[test]
+    fn rle() {
+        let data = [0x30, 7, 0, 0, 0x01, 1, 2, 0x82, 7];
+        assert_eq!(decompress(&data).unwrap(), [1, 2, 7, 7, 7, 7, 7]);
+    }
+
+    #[test]
+    fn huffman() {
+        //The root's children are both data, `0` for the left and `1` for the right one
+        let tree = [0x01, 0xC0, b'x', b'y'];
+        let data = [&[0x28, 4, 0, 0][..], &tree, &0b0110_u32.rotate_right(4).to_le_bytes()].concat();
+        assert_eq!(decompress(&data).unwrap(), b"xyyx");
+        //4 bit symbols, low nibble first
+        let tree = [0x01, 0xC0, 0x1, 0x2];
+        let data = [&[0x24, 2, 0, 0][..], &tree, &0b0111_u32.rotate_right(4).to_le_bytes()].concat();
+        assert_eq!(decompress(&data).unwrap(), [0x21, 0x22]);
+    }
+
+    #[test]
+    fn bad_reference() {
+        let data = [0x10, 3, 0, 0, 0b1000_0000, 0x00, 0x00];
+        assert!(matches!(decompress(&data), Err(Error::BadReference)));
+    }
+
+    #[test]
+    fn eof() {
+        assert!(matches!(decompress(&[0x10, 1]), Err(Error::UnexpectedEof)));
+        assert!(matches!(decompress(&[0x30, 5, 0, 0, 0x01, 1]), Err(Error::UnexpectedEof)));
+        assert!(matches!(decompress(&[0x10, 3, 0, 0, 0x00, 1]), Err(Error::UnexpectedEof)));
+        //Nothing is reserved for a size the input can't back
+        assert!(matches!(decompress(&[0x00, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF, 1]), Err(Error::UnexpectedEof)));
+    }
+
+    #[test]
+    fn bad_compression() {
+        assert!(matches!(decompress(&[0x40, 1, 0, 0, 0]), Err(Error::BadCompression(0x40))));
+    }
+
+    fn file(size: u8) -> Vec<u8> {
+        //8x8 L8 without mip levels, one sub texture
+        let header = [1, 0, 0x00, 7, 0];
+        let sub_texture = [8u16, 8, 0, 1024, 512, 0].map(u16::to_le_bytes).concat();
+        [&header[..], &sub_texture, &[0x30, size, 0, 0, 0x80 | 61, 0x55]].concat()
+    }
+
+    #[test]
+    fn parse_file() {
+        let t3x = T3x::parse_file(&file(64), true).unwrap();
+        assert_eq!((t3x.descriptor.width, t3x.descriptor.height, t3x.descriptor.max_mip), (8, 8, 0));
+        assert!(t3x.descriptor.format == Format::L8 && t3x.descriptor.mode == Mode::Ordinary && t3x.descriptor.vram);
+        let sub = t3x.sub_textures[0];
+        assert_eq!([sub.width, sub.height], [8, 8]);
+        assert_eq!([sub.left, sub.top, sub.right, sub.bottom], [0.0, 1.0, 0.5, 0.0]);
+        assert_eq!(t3x.data, [0x55; 64]);
+    }
+
+    #[test]
+    fn parse_file_wrong_size() {
+        assert!(matches!(T3x::parse_file(&file(63), false), Err(Error::WrongDataSize)));
+        assert!(matches!(T3x::parse_file(&file(64)[..10], false), Err(Error::UnexpectedEof)));
+    }
+}
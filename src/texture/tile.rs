@@ -0,0 +1,108 @@
+//! The PICA stores textures in 8x8 pixel tiles, with the pixels inside a tile in Morton (Z) order.
+//! Tiles go left to right, starting from the *bottom* row of the image, as texture coordinate `(0,0)` is the bottom left corner.
+//!
+//! Nothing in here touches the GPU, so it also builds on the host.
+
+///Index of a pixel inside of its 8x8 tile, `x` and `y` are `0..8`
+pub const fn morton(x: u32, y: u32) -> u32 {
+    (x & 1) | ((y & 1) << 1) | ((x & 2) << 1) | ((y & 2) << 2) | ((x & 4) << 2) | ((y & 4) << 3)
+}
+
+///Index of a pixel in the tiled layout, where `y == 0` is the top row of a linear image
+pub fn tiled_index(x: u32, y: u32, width: u32, height: u32) -> usize {
+    let y = height - 1 - y;
+    let tile = (y / 8) * (width / 8) + (x / 8);
+    (tile * 64 + morton(x & 7, y & 7)) as usize
+}
+
+///`width` and `height` must be multiples of 8.
+///`bitsize` is the size of a pixel in bits, one of `4`, `8`, `16`, `24` or `32`.
+///For 4 bit formats the first pixel of each pair is in the low nibble.
+pub fn tile(linear: &[u8], tiled: &mut [u8], width: u32, height: u32, bitsize: usize) {
+    for_each_pixel(width, height, bitsize, |linear_index, tiled_index| {
+        copy_pixel(linear, linear_index, tiled, tiled_index, bitsize)
+    });
+}
+
+///Inverse of `tile`
+pub fn untile(tiled: &[u8], linear: &mut [u8], width: u32, height: u32, bitsize: usize) {
+    for_each_pixel(width, height, bitsize, |linear_index, tiled_index| {
+        copy_pixel(tiled, tiled_index, linear, linear_index, bitsize)
+    });
+}
+
+fn for_each_pixel(width: u32, height: u32, bitsize: usize, mut f: impl FnMut(usize, usize)) {
+    assert!(width.is_multiple_of(8) && height.is_multiple_of(8), "Texture sides must be multiples of 8");
+    assert!(matches!(bitsize, 4 | 8 | 16 | 24 | 32), "Unsupported pixel size");
+    for y in 0..height {
+        for x in 0..width {
+            f((y * width + x) as usize, tiled_index(x, y, width, height));
+        }
+    }
+}
+
+fn copy_pixel(src: &[u8], src_index: usize, dst: &mut [u8], dst_index: usize, bitsize: usize) {
+    if bitsize == 4 {
+        let nibble = (src[src_index / 2] >> ((src_index & 1) * 4)) & 0xF;
+        let shift = (dst_index & 1) * 4;
+        let byte = &mut dst[dst_index / 2];
+        *byte = (*byte & !(0xF << shift)) | (nibble << shift);
+    } else {
+        let bytes = bitsize / 8;
+        dst[dst_index * bytes..][..bytes].copy_from_slice(&src[src_index * bytes..][..bytes]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn morton_covers_the_tile() {
+        let mut seen = [false; 64];
+        for y in 0..8 {
+            for x in 0..8 {
+                seen[morton(x, y) as usize] = true;
+            }
+        }
+        assert!(seen.iter().all(|&s| s));
+        assert_eq!([morton(1, 0), morton(0, 1), morton(2, 0), morton(7, 7)], [1, 2, 4, 63]);
+    }
+
+    #[test]
+    fn tiles_start_at_the_bottom_left() {
+        assert_eq!(tiled_index(0, 15, 16, 16), 0);
+        assert_eq!(tiled_index(1, 15, 16, 16), 1);
+        assert_eq!(tiled_index(0, 14, 16, 16), 2);
+        assert_eq!(tiled_index(8, 15, 16, 16), 64);
+        //Top left, in the first tile of the second tile row
+        assert_eq!(tiled_index(0, 0, 16, 16), 128 + 42);
+    }
+
+    #[test]
+    fn round_trip() {
+        let (width, height) = (16, 24);
+        for bitsize in [4, 8, 16, 24, 32] {
+            let size = (width * height) as usize * bitsize / 8;
+            let linear: Vec<u8> = (0..size).map(|i| (i * 7 + i / 251) as u8).collect();
+            let mut tiled = vec![0; size];
+            let mut back = vec![0; size];
+            tile(&linear, &mut tiled, width, height, bitsize);
+            assert_ne!(tiled, linear);
+            untile(&tiled, &mut back, width, height, bitsize);
+            assert_eq!(back, linear, "{bitsize} bit pixels");
+        }
+    }
+
+    #[test]
+    fn nibbles_low_first() {
+        //Bottom row of an 8x8 L4 image, pixels 2 and 3 are at Morton index 4 and 5, the third byte
+        let mut linear = vec![0; 32];
+        linear[28] = 0x21;
+        linear[29] = 0x43;
+        let mut tiled = vec![0; 32];
+        tile(&linear, &mut tiled, 8, 8, 4);
+        assert_eq!(tiled[0], 0x21);
+        assert_eq!(tiled[2], 0x43);
+    }
+}